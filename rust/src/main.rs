@@ -2,12 +2,12 @@
 use std::time::Instant;
 
 pub mod ranges;
-use crate::ranges::{mask::Mask, types::{Port, IP, Socket}, logic::Op};
+use crate::ranges::{mask::Mask, types::{Port, Protocol, IP, Socket}, logic::RangeSet};
 
 macro_rules! measure_time {
     ($code:block) => {{
         let start = Instant::now();
-        let result = $code;
+        let _result = $code;
         let duration = start.elapsed();
         duration.as_secs_f64()
     }};
@@ -22,33 +22,33 @@ fn equal_tests() {
     let range_2a: Socket = Socket::new(0, 1000);
     let range_2b: Socket = Socket::new(1100, 2000);
 
-    let range_1 = Op {ranges: vec![Box::new(range_1a), Box::new(range_1b)], range_of: None};
-    let range_2 = Op {ranges: vec![Box::new(range_2a), Box::new(range_2b)], range_of: None};
+    let range_1 = RangeSet::from_ranges(vec![range_1a, range_1b]).unwrap();
+    let range_2 = RangeSet::from_ranges(vec![range_2a, range_2b]).unwrap();
 
 
     println!("invert");
-    println!("{}", range_1.clone());
-    println!("{}", !range_1.clone());
+    println!("{}", range_1);
+    println!("{}", !&range_1);
     println!("logical and");
-    println!("{}", range_1.clone());
-    println!("{}", range_2.clone());
-    println!("{}", range_1.clone() & range_2.clone());
+    println!("{}", range_1);
+    println!("{}", range_2);
+    println!("{}", &range_1 & &range_2);
     println!("logical or");
-    println!("{}", range_1.clone());
-    println!("{}", range_2.clone());
-    println!("{}", range_1.clone() | range_2.clone());
+    println!("{}", range_1);
+    println!("{}", range_2);
+    println!("{}", &range_1 | &range_2);
     println!("logical sub var 1");
-    println!("{}", range_1.clone());
-    println!("{}", range_2.clone());
-    println!("{}", range_1.clone() - range_2.clone());
+    println!("{}", range_1);
+    println!("{}", range_2);
+    println!("{}", &range_1 - &range_2);
     println!("logical sub var 2");
-    println!("{}", range_1.clone());
-    println!("{}", range_2.clone());
-    println!("{}", range_2.clone() - range_1.clone());
+    println!("{}", range_1);
+    println!("{}", range_2);
+    println!("{}", &range_2 - &range_1);
     println!("logical xor");
-    println!("{}", range_1.clone());
-    println!("{}", range_2.clone());
-    println!("{}", range_1.clone() ^ range_2.clone());
+    println!("{}", range_1);
+    println!("{}", range_2);
+    println!("{}", &range_1 ^ &range_2);
 
 }
 
@@ -58,25 +58,27 @@ fn equal_mask_tests() {
     let mask_1 = Mask{
         range_ips: vec![IP::new(0, 0)],
         range_ports: vec![Port::new(0, 1000), Port::new(1500, 2000)],
+        range_protocols: vec![Protocol::TCP],
     };
 
     let mask_2 = Mask{
         range_ips: vec![IP::new(0, 0)],
         range_ports: vec![Port::new(0, 1000), Port::new(1100, 2000)],
+        range_protocols: vec![Protocol::TCP],
     };
 
     println!("collapsed mask 1");
-    println!("{}", mask_1.to_collapsed().to_string());
+    println!("{}", mask_1.to_collapsed());
     println!("collapsed mask 2");
-    println!("{}", mask_2.to_collapsed().to_string());
+    println!("{}", mask_2.to_collapsed());
     println!("invert mask 1");
-    println!("{}", !mask_1.to_collapsed());
+    println!("{}", !&mask_1.to_collapsed());
     println!("invert mask 2");
-    println!("{}", !mask_2.to_collapsed());
+    println!("{}", !&mask_2.to_collapsed());
     println!("or masks");
-    println!("{}", mask_1.to_collapsed() | mask_2.to_collapsed());
+    println!("{}", &mask_1.to_collapsed() | &mask_2.to_collapsed());
     println!("and masks");
-    println!("{}", mask_1.to_collapsed() & mask_2.to_collapsed());
+    println!("{}", &mask_1.to_collapsed() & &mask_2.to_collapsed());
     println!("\n");
 
 }
@@ -89,41 +91,46 @@ fn perf_tests(power: i32) -> String {
     let rules_1 = Mask{
         range_ips: vec![IP::new(0, count)],
         range_ports: vec![Port::new(0, 1500)],
+        range_protocols: vec![Protocol::TCP],
     };
 
     let rules_2 = Mask{
         range_ips: vec![IP::new(0, count)],
         range_ports: vec![Port::new(0, 1000)],
+        range_protocols: vec![Protocol::TCP],
     };
 
+    let collapsed_1 = rules_1.to_collapsed_plane(Protocol::TCP.begin);
+    let collapsed_2 = rules_2.to_collapsed_plane(Protocol::TCP.begin);
+
     println!("performance tests started for {count} ranges");
 
     let total_logic_or = measure_time!({
-        _ = rules_1.clone().to_collapsed() | rules_2.clone().to_collapsed()
+        _ = &collapsed_1 | &collapsed_2
     });
 
     println!("logic or: {:?}", total_logic_or);
 
     let total_logic_and = measure_time!({
-        _ = rules_1.clone().to_collapsed() & rules_2.clone().to_collapsed()
+        _ = &collapsed_1 & &collapsed_2
     });
 
     println!("logic and: {:?}", total_logic_and);
 
     let total_logic_xor = measure_time!({
-        _ = rules_1.clone().to_collapsed() ^ rules_2.clone().to_collapsed()
+        _ = &collapsed_1 ^ &collapsed_2
     });
 
     println!("logic xor: {:?}", total_logic_xor);
 
     let total_logic_sub_1 = measure_time!({
-        _ = rules_1.clone().to_collapsed() - rules_2.clone().to_collapsed()
+        _ = &collapsed_1 - &collapsed_2
     });
 
     println!("logic sub 1: {:?}", total_logic_sub_1);
 
     let total_logic_sub_2 = measure_time!({
-        _ = rules_2.clone().to_collapsed() - rules_1.clone().to_collapsed()
+        _ = &collapsed_2 - &collapsed_1
     });
 
     println!("logic sub 2: {:?}", total_logic_sub_2);
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::net::Ipv4Addr;
+
+use crate::ranges::logic::RangeSet;
+use crate::ranges::types::IP;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    pub addr: Ipv4Addr,
+    pub prefix: u8,
+}
+
+
+impl Cidr {
+
+    // Host bits of `addr` under the prefix are cleared.
+    pub fn new(addr: Ipv4Addr, prefix: u8) -> Self {
+        let prefix = prefix.min(32);
+        let begin = u32::from(addr) & !host_mask(prefix);
+        Self { addr: Ipv4Addr::from(begin), prefix }
+    }
+
+    pub fn to_ip(&self) -> IP {
+        let begin = u32::from(self.addr);
+        IP::new(begin, begin | host_mask(self.prefix))
+    }
+
+}
+
+
+fn host_mask(prefix: u8) -> u32 { u32::MAX.checked_shr(prefix as u32).unwrap_or(0) }
+
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+
+impl From<Cidr> for IP {
+
+    fn from(cidr: Cidr) -> Self { cidr.to_ip() }
+
+}
+
+
+// Splits `begin..=end` of a `bits` wide space into the fewest aligned
+// power-of-two blocks, always taking the largest block that starts at `begin`
+// and stays inside the interval. Yields (block begin, prefix length) pairs.
+pub(crate) fn prefix_blocks(begin: u64, end: u64, bits: u32) -> Vec<(u64, u32)> {
+
+    let mut blocks: Vec<(u64, u32)> = vec![];
+    let mut begin = begin;
+
+    while begin <= end {
+
+        let mut size: u64 = if begin == 0 { 1 << bits } else { 1 << begin.trailing_zeros().min(bits) };
+        while begin + size - 1 > end {
+            size >>= 1;
+        }
+
+        blocks.push((begin, bits - size.trailing_zeros()));
+        begin += size;
+
+    }
+
+    blocks
+
+}
+
+
+impl RangeSet<IP> {
+
+    pub fn to_cidrs(&self) -> Vec<Cidr> {
+
+        let mut cidrs: Vec<Cidr> = vec![];
+
+        for range in self.ranges() {
+            for (begin, prefix) in prefix_blocks(range.begin as u64, range.end as u64, 32) {
+                cidrs.push(Cidr { addr: Ipv4Addr::from(begin as u32), prefix: prefix as u8 });
+            }
+        }
+
+        cidrs
+
+    }
+
+    pub fn from_cidrs<I: IntoIterator<Item = Cidr>>(cidrs: I) -> Self {
+        RangeSet::normalized(cidrs.into_iter().map(IP::from).collect())
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ranges::types::MAX_IP;
+
+    fn cidr(s: &str, prefix: u8) -> Cidr { Cidr::new(s.parse().unwrap(), prefix) }
+
+    #[test]
+    fn test_cidr() {
+
+        assert_eq!(cidr("10.1.2.3", 8), cidr("10.0.0.0", 8));
+        assert_eq!(cidr("10.0.0.0", 8).to_ip(), IP::new(167772160, 184549375));
+        assert_eq!(cidr("0.0.0.0", 0).to_ip(), IP::new(0, MAX_IP));
+        assert_eq!(cidr("10.0.0.0", 8).to_string(), "10.0.0.0/8");
+
+    }
+
+    #[test]
+    fn test_to_cidrs() {
+
+        let op = RangeSet::from_ranges(vec![IP::new(167772161, 167772170)]).unwrap();
+        assert_eq!(op.to_cidrs(), vec![
+            cidr("10.0.0.1", 32),
+            cidr("10.0.0.2", 31),
+            cidr("10.0.0.4", 30),
+            cidr("10.0.0.8", 31),
+            cidr("10.0.0.10", 32),
+        ]);
+
+        assert_eq!(RangeSet::<IP>::full().to_cidrs(), vec![cidr("0.0.0.0", 0)]);
+        assert_eq!(RangeSet::<IP>::empty().to_cidrs(), vec![]);
+
+        let op = RangeSet::from_ranges(vec![IP::new(1, MAX_IP - 1)]).unwrap();
+        assert_eq!(op.to_cidrs().len(), 62);
+
+    }
+
+    #[test]
+    fn test_cidrs_round_trip() {
+
+        // Pseudo-random intervals spread over the whole 32-bit space.
+        let mut seed: u64 = 42;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 32) as u32
+        };
+
+        for _ in 0..200 {
+
+            let ranges: Vec<IP> = (0..8)
+                .map(|_| {
+                    let (a, b) = (next(), next());
+                    IP::new(a.min(b), a.max(b))
+                })
+                .collect();
+
+            let op = RangeSet::from_ranges(ranges).unwrap();
+            let cidrs = op.to_cidrs();
+
+            assert_eq!(RangeSet::from_cidrs(cidrs.clone()), op);
+            assert_eq!(
+                cidrs.iter().map(|x| 1u128 << (32 - x.prefix)).sum::<u128>(),
+                op.len_values().unwrap()
+            );
+
+        }
+
+        for op in [RangeSet::<IP>::full(), RangeSet::empty(), RangeSet::from_ranges(vec![IP::new(MAX_IP, MAX_IP)]).unwrap()] {
+            assert_eq!(RangeSet::from_cidrs(op.to_cidrs()), op);
+        }
+
+    }
+
+}
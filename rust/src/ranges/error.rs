@@ -0,0 +1,51 @@
+use std::fmt;
+
+use super::types::TypeRange;
+
+
+// Generic over the bound type of the offending range, u64 for IPv4 types.
+#[derive(Debug, PartialEq, Clone)]
+pub enum RangeError<B = u64> {
+    Inverted { begin: B, end: B },
+    OutOfBounds { value: B, max: B },
+    TypeMismatch { expected: TypeRange, found: TypeRange },
+    InvalidAddress(String),
+    InvalidPrefix(String),
+}
+
+
+impl<B> RangeError<B> {
+
+    // Converts the bounds carried by the error, e.g. to report a u16 port error as u64.
+    pub fn map_bound<C>(self, f: impl Fn(B) -> C) -> RangeError<C> {
+        match self {
+            RangeError::Inverted { begin, end } => RangeError::Inverted { begin: f(begin), end: f(end) },
+            RangeError::OutOfBounds { value, max } => RangeError::OutOfBounds { value: f(value), max: f(max) },
+            RangeError::TypeMismatch { expected, found } => RangeError::TypeMismatch { expected, found },
+            RangeError::InvalidAddress(input) => RangeError::InvalidAddress(input),
+            RangeError::InvalidPrefix(input) => RangeError::InvalidPrefix(input),
+        }
+    }
+
+}
+
+
+impl<B: fmt::Display> fmt::Display for RangeError<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeError::Inverted { begin, end } =>
+                write!(f, "range begin {begin} is greater than end {end}"),
+            RangeError::OutOfBounds { value, max } =>
+                write!(f, "value {value} is out of bounds, max is {max}"),
+            RangeError::TypeMismatch { expected, found } =>
+                write!(f, "expected {expected:?} range, found {found:?}"),
+            RangeError::InvalidAddress(input) =>
+                write!(f, "invalid IP address {input:?}"),
+            RangeError::InvalidPrefix(input) =>
+                write!(f, "invalid prefix length {input:?}"),
+        }
+    }
+}
+
+
+impl<B: fmt::Debug + fmt::Display> std::error::Error for RangeError<B> {}
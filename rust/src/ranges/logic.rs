@@ -1,229 +1,692 @@
-use std::cmp::{min, max};
+use std::cmp::{min, max, Reverse};
+use std::collections::BinaryHeap;
 
 use std::fmt::{Display, Formatter, Result};
-use std::ops::{BitAnd, BitOr, BitXor, Not, Sub};
+use std::ops::{
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign,
+};
 
-use super::types::{Port, IP, Socket, Range, TypeRange};
+use super::error::RangeError;
+use super::types::{Bound, Range, TypeRange};
 
 
-#[derive(Clone)]
-pub struct Op {
-    pub ranges: Vec<Box<dyn Range>>,
-    pub range_of: Option<TypeRange>,
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RangeSet<T: Range> {
+    ranges: Vec<T>,
 }
 
 
-impl Display for Op {
+impl<T: Range> Display for RangeSet<T> {
 
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(
             f,
-            "{}",
-            format!(
-                "[{}]",
-                self.ranges.iter()
-                    .map(|x| x.to_string())
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            )
+            "[{}]",
+            self.ranges.iter()
+                .map(|x| if f.alternate() { format!("{x:#}") } else { x.to_string() })
+                .collect::<Vec<String>>()
+                .join(", ")
         )
 
     }
 }
 
 
-impl Op {
+impl<T: Range> RangeSet<T> {
 
-    fn get_range_of(&self) -> TypeRange {
+    pub fn from_ranges(ranges: Vec<T>) -> std::result::Result<Self, RangeError<T::Bound>> {
 
-        if self.range_of != None {
-            self.range_of.clone().unwrap()
-        } else {
-            self.ranges[0].range_of()
+        for range in &ranges {
+            T::try_from_bounds(range.begin(), range.end())?;
+        }
+
+        Ok(Self::normalized(ranges))
+
+    }
+
+    // Builds a set from runtime-tagged bounds, e.g. parsed from config,
+    // rejecting any entry tagged with another range type.
+    pub fn from_tagged<I>(items: I) -> std::result::Result<Self, RangeError<T::Bound>>
+        where I: IntoIterator<Item = (TypeRange, T::Bound, T::Bound)>
+    {
+
+        let mut ranges: Vec<T> = vec![];
+
+        for (range_of, begin, end) in items {
+            if range_of != T::RANGE_OF {
+                return Err(RangeError::TypeMismatch { expected: T::RANGE_OF, found: range_of });
+            }
+            ranges.push(T::try_from_bounds(begin, end)?);
+        }
+
+        Ok(Self::normalized(ranges))
+
+    }
+
+    // Like `from_ranges`, but drops inverted or out of bounds ranges instead of failing,
+    // for masks whose fields were filled in without `Mask::try_new`.
+    pub(crate) fn from_valid_ranges(mut ranges: Vec<T>) -> Self {
+        ranges.retain(|x| T::try_from_bounds(x.begin(), x.end()).is_ok());
+        Self::normalized(ranges)
+    }
+
+    // Sorts and merges overlapping or adjacent intervals, bounds must be valid already.
+    pub(crate) fn normalized(mut intervals: Vec<T>) -> Self {
+
+        if intervals.is_empty() {
+            return RangeSet::empty();
+        }
+
+        intervals.sort_by_key(|a| a.begin());
+
+        let mut ranges: Vec<T> = Vec::with_capacity(intervals.len());
+
+        for i in &intervals {
+            push_merged(&mut ranges, i.begin(), i.end());
+        }
+
+        RangeSet {ranges}
+
+    }
+
+    // Builds a set from ranges sorted by begin, merging them as they come without a sort.
+    pub(crate) fn from_sorted_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        RangeSet {ranges: Coalesced::new(iter).collect()}
+    }
+
+    pub fn ranges(&self) -> &[T] { &self.ranges }
+
+    pub fn empty() -> Self { RangeSet {ranges: vec![]} }
+
+    pub fn full() -> Self { RangeSet {ranges: vec![T::from_bounds(T::MIN, T::MAX)]} }
+
+    pub fn is_empty(&self) -> bool { self.ranges.is_empty() }
+
+    pub fn is_full(&self) -> bool {
+        self.ranges.len() == 1 && self.ranges[0].begin() == T::MIN && self.ranges[0].end() == T::MAX
+    }
+
+    // Index of the first interval whose end is not below value.
+    fn lower_bound(&self, value: T::Bound) -> usize {
+        self.ranges.partition_point(|x| x.end() < value)
+    }
+
+    pub fn contains(&self, value: T::Bound) -> bool {
+        self.contains_range(value, value)
+    }
+
+    pub fn contains_range(&self, begin: T::Bound, end: T::Bound) -> bool {
+
+        if begin > end {
+            return false;
         }
 
+        match self.ranges.get(self.lower_bound(begin)) {
+            Some(range) => range.begin() <= begin && end <= range.end(),
+            None => false,
+        }
+
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.ranges.iter().all(|x| other.contains_range(x.begin(), x.end()))
     }
 
+    pub fn is_superset(&self, other: &Self) -> bool { other.is_subset(self) }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+
+        let mut l_cnt: usize = 0;
+        let mut r_cnt: usize = 0;
+
+        while l_cnt < self.ranges.len() && r_cnt < other.ranges.len() {
+
+            let left = &self.ranges[l_cnt];
+            let right = &other.ranges[r_cnt];
+
+            if right.begin() <= left.end() && left.begin() <= right.end() {
+                return false;
+            }
+
+            if left.end() > right.end() {
+                r_cnt += 1;
+            } else {
+                l_cnt += 1;
+            }
+
+        }
+
+        true
+
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool { !self.is_disjoint(other) }
+
+    pub fn union_all<'a, I>(sets: I) -> Self where I: IntoIterator<Item = &'a Self>, T: 'a {
+        sweep(&sets.into_iter().collect::<Vec<_>>(), 1)
+    }
+
+    // The intersection of no sets is the whole space.
+    pub fn intersect_all<'a, I>(sets: I) -> Self where I: IntoIterator<Item = &'a Self>, T: 'a {
+        let sets: Vec<&Self> = sets.into_iter().collect();
+        sweep(&sets, sets.len())
+    }
+
+    // Values that belong to at least `k` of the given sets.
+    pub fn covered_by_at_least<'a, I>(k: usize, sets: I) -> Self where I: IntoIterator<Item = &'a Self>, T: 'a {
+        sweep(&sets.into_iter().collect::<Vec<_>>(), k)
+    }
+
+    // Union with a stream of ranges sorted by begin, such as `Mask::sockets`,
+    // without collecting the stream first. Unsorted input loses ranges.
+    pub(crate) fn union_sorted<I: IntoIterator<Item = T>>(&self, iter: I) -> Self {
+
+        let mut ranges: Vec<T> = Vec::with_capacity(self.ranges.len());
+        let mut l_cnt: usize = 0;
+
+        for range in Coalesced::new(iter) {
+            while l_cnt < self.ranges.len() && self.ranges[l_cnt].begin() <= range.begin() {
+                push_merged(&mut ranges, self.ranges[l_cnt].begin(), self.ranges[l_cnt].end());
+                l_cnt += 1;
+            }
+            push_merged(&mut ranges, range.begin(), range.end());
+        }
+
+        for range in &self.ranges[l_cnt..] {
+            push_merged(&mut ranges, range.begin(), range.end());
+        }
+
+        RangeSet {ranges}
+
+    }
+
+    // Intersection with a stream of ranges sorted by begin.
+    pub(crate) fn intersect_sorted<I: IntoIterator<Item = T>>(&self, iter: I) -> Self {
+
+        let mut ranges: Vec<T> = vec![];
+        let mut l_cnt: usize = 0;
+
+        for range in Coalesced::new(iter) {
+
+            while l_cnt < self.ranges.len() && self.ranges[l_cnt].end() < range.begin() {
+                l_cnt += 1;
+            }
+
+            for left in self.ranges[l_cnt..].iter().take_while(|x| x.begin() <= range.end()) {
+                ranges.push(T::from_bounds(max(left.begin(), range.begin()), min(left.end(), range.end())));
+            }
+
+        }
+
+        RangeSet {ranges}
+
+    }
+
+    pub fn count_ranges(&self) -> usize { self.ranges.len() }
+
+    // Total number of values covered, None when it does not fit in u128,
+    // which only happens for IPv6 spaces (a full IPv6 set holds 2^128 values).
+    pub fn len_values(&self) -> Option<u128> {
+        self.ranges.iter().try_fold(0u128, |acc, x| acc.checked_add(span(x)?))
+    }
+
+    pub fn largest_range(&self) -> Option<T> {
+        self.ranges.iter().rev().max_by_key(|x| x.end().distance(x.begin())).copied()
+    }
+
+    pub fn smallest_range(&self) -> Option<T> {
+        self.ranges.iter().min_by_key(|x| x.end().distance(x.begin())).copied()
+    }
+
+    // Fraction of the whole value space covered, from 0.0 to 1.0.
+    pub fn coverage(&self) -> f64 {
+        let covered: f64 = self.ranges.iter().map(|x| x.end().distance(x.begin()).to_f64() + 1.0).sum();
+        covered / (T::MAX.to_f64() + 1.0)
+    }
+
+}
+
+
+// Appends an interval that does not start before the last one,
+// merging them when they overlap or touch.
+fn push_merged<T: Range>(ranges: &mut Vec<T>, begin: T::Bound, end: T::Bound) {
+    push_merged_after(ranges, 0, begin, end);
 }
 
 
-fn build_from(range_of: TypeRange, begin: u64, end: u64) -> Box<dyn Range> {
+// Same as `push_merged`, leaving the first `base` ranges untouched.
+fn push_merged_after<T: Range>(ranges: &mut Vec<T>, base: usize, begin: T::Bound, end: T::Bound) {
 
-    match range_of {
-        TypeRange::Port => Box::new(Port{begin, end}),
-        TypeRange::IP => Box::new(IP{begin, end} ),
-        TypeRange::Socket => Box::new(Socket{begin, end}),
+    if ranges.len() > base
+        && let Some(last) = ranges.last_mut()
+        && (begin <= last.end() || last.end().succ() == begin)
+    {
+        let end: T::Bound = max(last.end(), end);
+        last.set_end(end);
+        return;
     }
 
+    ranges.push(T::from_bounds(begin, end));
+
 }
 
 
-impl Not for Op {
+// Merges the overlapping or touching neighbours of a stream sorted by begin.
+struct Coalesced<T: Range, I: Iterator<Item = T>> {
+    iter: I,
+    pending: Option<T>,
+}
 
-    type Output = Self;
 
-    fn not(self) -> Self::Output {
+impl<T: Range, I: Iterator<Item = T>> Coalesced<T, I> {
 
-        let mut ranges: Vec<Box<dyn Range>> = vec![];
-        let range_of = self.get_range_of();
+    fn new<J: IntoIterator<IntoIter = I>>(iter: J) -> Self { Coalesced { iter: iter.into_iter(), pending: None } }
 
-        if self.ranges[0].begin() != 0 {
-            let begin: u64 = 0;
-            let end: u64 = self.ranges[0].begin() - 1;
-            ranges.push(build_from(range_of.clone(), begin, end));
-        }
+}
 
-        for i in 0..self.ranges.len() - 1 {
 
-            let current = &self.ranges[i];
-            let next = &self.ranges[i + 1];
+impl<T: Range, I: Iterator<Item = T>> Iterator for Coalesced<T, I> {
 
-            let begin = current.end() + 1;
-            let end = next.begin() - 1;
+    type Item = T;
 
-            if begin <= end {
-                ranges.push(build_from(range_of.clone(), begin, end));
+    fn next(&mut self) -> Option<T> {
+
+        for range in self.iter.by_ref() {
+            match &mut self.pending {
+                Some(last) if range.begin() <= last.end() || last.end().succ() == range.begin() => {
+                    debug_assert!(last.begin() <= range.begin(), "ranges must be sorted by begin");
+                    let end = max(last.end(), range.end());
+                    last.set_end(end);
+                }
+                Some(_) => {
+                    debug_assert!(self.pending.is_some_and(|x| x.begin() <= range.begin()), "ranges must be sorted by begin");
+                    return self.pending.replace(range);
+                }
+                None => self.pending = Some(range),
             }
         }
 
-        let max_ip_num = self.ranges.last().unwrap().max();
-        if self.ranges.last().unwrap().end() != max_ip_num {
-            let begin: u64 = self.ranges.last().unwrap().end() + 1;
-            let end: u64 = max_ip_num;
-            ranges.push(build_from(range_of.clone(), begin, end));
+        self.pending.take()
+
+    }
+
+}
+
+
+// Walks the boundaries of all sets in order with a k-way heap merge and keeps
+// the parts covered by at least `k` of them. Event 2 * i is the begin of
+// ranges[i] in its set, positioned right before it, event 2 * i + 1 is its
+// end, positioned right after it. Positions are (value, 0 before / 1 after),
+// so nothing overflows at the end of the space.
+fn sweep<T: Range>(sets: &[&RangeSet<T>], k: usize) -> RangeSet<T> {
+
+    if k == 0 {
+        return RangeSet::full();
+    }
+
+    let position = |set: usize, event: usize| -> Option<(T::Bound, u8)> {
+        sets[set].ranges.get(event / 2).map(|x| if event.is_multiple_of(2) { (x.begin(), 0) } else { (x.end(), 1) })
+    };
+
+    let mut heap = BinaryHeap::with_capacity(sets.len());
+    for set in 0..sets.len() {
+        if let Some(pos) = position(set, 0) {
+            heap.push(Reverse((pos, set, 0usize)));
         }
+    }
 
-        Op {ranges, range_of: Some(range_of)}
+    let mut ranges: Vec<T> = vec![];
+    let mut depth: usize = 0;
+    let mut open: Option<T::Bound> = None;
+
+    while let Some(Reverse(((pos, _), set, event))) = heap.pop() {
+
+        if event.is_multiple_of(2) {
+            depth += 1;
+            if depth == k {
+                open = Some(pos);
+            }
+        } else {
+            if depth == k && let Some(begin) = open.take() {
+                push_merged(&mut ranges, begin, pos);
+            }
+            depth -= 1;
+        }
+
+        if let Some(next) = position(set, event + 1) {
+            heap.push(Reverse((next, set, event + 1)));
+        }
 
     }
 
+    RangeSet {ranges}
+
 }
 
 
-impl BitOr for Op {
+fn span<T: Range>(range: &T) -> Option<u128> {
+    range.end().distance(range.begin()).to_u128()?.checked_add(1)
+}
 
-    type Output = Self;
 
-    fn bitor(self, rhs: Self) -> Self::Output {
+impl<T: Range> Not for &RangeSet<T> {
+
+    type Output = RangeSet<T>;
 
-        let range_of = self.get_range_of();
+    fn not(self) -> Self::Output {
 
-        let mut intervals = self.ranges;
-        intervals.extend(rhs.ranges);
+        if self.is_empty() {
+            return RangeSet::full();
+        }
 
-        intervals.sort_by(|a, b| a.begin().cmp(&b.begin()));
+        let mut ranges: Vec<T> = Vec::with_capacity(self.ranges.len() + 1);
 
-        let mut ranges: Vec<Box<dyn Range>> = vec![];
-        ranges.push(intervals.first().unwrap().clone_dyn());
+        if self.ranges[0].begin() != T::MIN {
+            let begin: T::Bound = T::MIN;
+            let end: T::Bound = self.ranges[0].begin().pred();
+            ranges.push(T::from_bounds(begin, end));
+        }
 
-        for i in intervals.iter_mut().skip(1) {
+        for pair in self.ranges.windows(2) {
 
-            if (
-                ranges.iter().last().unwrap().begin() <= i.begin()
-                    &&
-                i.begin() <= ranges.iter().last().unwrap().end()
-            ) || (ranges.iter().last().unwrap().end() + 1 == i.end())    {
-                let end: u64 = max(ranges.iter().last().unwrap().end(), i.end());
-                ranges.iter_mut().last().unwrap().set_end(end);
-            } else {
-                ranges.push(build_from(range_of.clone(), i.begin(), i.end()));
+            let begin = pair[0].end().succ();
+            let end = pair[1].begin().pred();
+
+            if begin <= end {
+                ranges.push(T::from_bounds(begin, end));
             }
+        }
 
+        let last_end = self.ranges.last().unwrap().end();
+        if last_end != T::MAX {
+            ranges.push(T::from_bounds(last_end.succ(), T::MAX));
         }
 
-        Op {ranges, range_of: Some(range_of)}
+        RangeSet {ranges}
 
     }
 
 }
 
 
-impl BitAnd for Op {
+impl<T: Range> Not for RangeSet<T> {
 
     type Output = Self;
 
-    fn bitand(self, rhs: Self) -> Self::Output {
+    fn not(self) -> Self::Output { !&self }
 
-        let mut ranges: Vec<Box<dyn Range>> = vec![];
-        let range_of = self.get_range_of();
+}
 
-        let mut l_cnt: usize = 0;
-        let mut r_cnt: usize = 0;
 
-        while l_cnt < self.ranges.len() && r_cnt < rhs.ranges.len() {
+// The binary operators work on one buffer: the left operand is its first `len`
+// ranges, the result is pushed after them and the prefix is dropped at the end.
+// The in-place and owned forms pass their own buffer, so they reuse its allocation.
+fn apply<T: Range>(buf: &mut Vec<T>, rhs: &[T], kernel: fn(&mut Vec<T>, usize, &[T])) {
+    let len = buf.len();
+    kernel(buf, len, rhs);
+    buf.drain(..len);
+}
 
-            if rhs.ranges[r_cnt].begin() <= self.ranges[l_cnt].end()
-                 &&
-               self.ranges[l_cnt].begin() <= rhs.ranges[r_cnt].end()
-            {
 
-                let left: u64 = max(self.ranges[l_cnt].begin(), rhs.ranges[r_cnt].begin());
-                let right: u64 = min(self.ranges[l_cnt].end(), rhs.ranges[r_cnt].end());
-                ranges.push(build_from(range_of.clone(), left, right));
-            }
+fn bounds_at<T: Range>(ranges: &[T], len: usize, i: usize) -> Option<(T::Bound, T::Bound)> {
+    (i < len).then(|| (ranges[i].begin(), ranges[i].end()))
+}
+
+
+// Both operands are canonical, so a two-pointer merge replaces the sort.
+fn or_into<T: Range>(buf: &mut Vec<T>, len: usize, rhs: &[T]) {
+
+    let mut l_cnt: usize = 0;
+    let mut r_cnt: usize = 0;
 
-            if self.ranges[l_cnt].end() > rhs.ranges[r_cnt].end() {
+    while l_cnt < len || r_cnt < rhs.len() {
+
+        let next = if l_cnt < len && (r_cnt == rhs.len() || buf[l_cnt].begin() <= rhs[r_cnt].begin()) {
+            l_cnt += 1;
+            buf[l_cnt - 1]
+        } else {
+            r_cnt += 1;
+            rhs[r_cnt - 1]
+        };
+
+        push_merged_after(buf, len, next.begin(), next.end());
+
+    }
+
+}
+
+
+fn and_into<T: Range>(buf: &mut Vec<T>, len: usize, rhs: &[T]) {
+
+    let mut l_cnt: usize = 0;
+    let mut r_cnt: usize = 0;
+
+    while l_cnt < len && r_cnt < rhs.len() {
+
+        let (left, right) = (buf[l_cnt], rhs[r_cnt]);
+
+        if right.begin() <= left.end() && left.begin() <= right.end() {
+            buf.push(T::from_bounds(max(left.begin(), right.begin()), min(left.end(), right.end())));
+        }
+
+        if left.end() > right.end() {
+            r_cnt += 1;
+        } else {
+            l_cnt += 1;
+        }
+
+    }
+
+}
+
+
+fn sub_into<T: Range>(buf: &mut Vec<T>, len: usize, rhs: &[T]) {
+
+    let mut l_cnt: usize = 0;
+    let mut r_cnt: usize = 0;
+
+    // The part of the left range l_cnt that is not consumed yet.
+    let mut current = bounds_at(buf, len, 0);
+
+    while let Some((begin, end)) = current {
+
+        match rhs.get(r_cnt) {
+            None => {
+                buf.push(T::from_bounds(begin, end));
+                l_cnt += 1;
+            }
+            Some(right) if right.end() < begin => {
                 r_cnt += 1;
-            } else {
+                continue;
+            }
+            Some(right) if end < right.begin() => {
+                buf.push(T::from_bounds(begin, end));
+                l_cnt += 1;
+            }
+            Some(right) => {
+                if begin < right.begin() {
+                    buf.push(T::from_bounds(begin, right.begin().pred()));
+                }
+                if right.end() < end {
+                    current = Some((right.end().succ(), end));
+                    r_cnt += 1;
+                    continue;
+                }
                 l_cnt += 1;
             }
-
         }
 
-        Op {ranges, range_of: Some(range_of)}
+        current = bounds_at(buf, len, l_cnt);
 
     }
 
 }
 
 
-impl Sub for Op {
+fn xor_into<T: Range>(buf: &mut Vec<T>, len: usize, rhs: &[T]) {
 
-    type Output = Self;
+    let mut l_cnt: usize = 0;
+    let mut r_cnt: usize = 0;
+
+    // The parts of the left range l_cnt and of rhs[r_cnt] that are not consumed yet.
+    let mut left = bounds_at(buf, len, 0);
+    let mut right = bounds_at(rhs, rhs.len(), 0);
 
-    fn sub(self, rhs: Self) -> Self::Output { self & !rhs }
+    loop {
+
+        let (l_step, r_step) = match (left, right) {
+            (None, None) => break,
+            (Some((begin, end)), None) => {
+                push_merged_after(buf, len, begin, end);
+                (true, false)
+            }
+            (None, Some((begin, end))) => {
+                push_merged_after(buf, len, begin, end);
+                (false, true)
+            }
+            (Some((l_begin, l_end)), Some((r_begin, r_end))) => {
+                if l_end < r_begin {
+                    push_merged_after(buf, len, l_begin, l_end);
+                    (true, false)
+                } else if r_end < l_begin {
+                    push_merged_after(buf, len, r_begin, r_end);
+                    (false, true)
+                } else if l_begin < r_begin {
+                    push_merged_after(buf, len, l_begin, r_begin.pred());
+                    left = Some((r_begin, l_end));
+                    (false, false)
+                } else if r_begin < l_begin {
+                    push_merged_after(buf, len, r_begin, l_begin.pred());
+                    right = Some((l_begin, r_end));
+                    (false, false)
+                } else if l_end < r_end {
+                    right = Some((l_end.succ(), r_end));
+                    (true, false)
+                } else if r_end < l_end {
+                    left = Some((r_end.succ(), l_end));
+                    (false, true)
+                } else {
+                    (true, true)
+                }
+            }
+        };
+
+        if l_step {
+            l_cnt += 1;
+            left = bounds_at(buf, len, l_cnt);
+        }
+
+        if r_step {
+            r_cnt += 1;
+            right = bounds_at(rhs, rhs.len(), r_cnt);
+        }
+
+    }
 
 }
 
 
-impl BitXor for Op {
+impl<T: Range> FromIterator<RangeSet<T>> for RangeSet<T> {
 
-    type Output = Self;
+    fn from_iter<I: IntoIterator<Item = RangeSet<T>>>(iter: I) -> Self {
+        let sets: Vec<RangeSet<T>> = iter.into_iter().collect();
+        RangeSet::union_all(&sets)
+    }
+
+}
 
-    fn bitxor(self, rhs: Self) -> Self::Output {
 
-        let clone_self = Op {ranges: self.ranges.clone(), range_of: self.range_of.clone()};
-        let clone_rhs = Op {ranges: rhs.ranges.clone(), range_of: rhs.range_of.clone()};
+impl<T: Range> Default for RangeSet<T> {
 
-        self - rhs | clone_rhs - clone_self
+    fn default() -> Self { RangeSet::empty() }
 
+}
+
+
+impl<T: Range> Extend<RangeSet<T>> for RangeSet<T> {
+
+    fn extend<I: IntoIterator<Item = RangeSet<T>>>(&mut self, iter: I) {
+        let sets: Vec<RangeSet<T>> = iter.into_iter().collect();
+        *self = RangeSet::union_all(std::iter::once(&*self).chain(&sets));
     }
 
 }
 
 
+// Borrowed forms copy the left operand into a fresh buffer, owned and in-place
+// forms run the kernel on their own ranges.
+macro_rules! impl_binop {
+    ($imp:ident, $method:ident, $imp_assign:ident, $method_assign:ident, $kernel:ident) => {
+
+        impl<T: Range> $imp<&RangeSet<T>> for &RangeSet<T> {
+
+            type Output = RangeSet<T>;
+
+            fn $method(self, rhs: &RangeSet<T>) -> Self::Output {
+                let mut ranges: Vec<T> = Vec::with_capacity(2 * self.ranges.len() + rhs.ranges.len());
+                ranges.extend_from_slice(&self.ranges);
+                apply(&mut ranges, &rhs.ranges, $kernel);
+                RangeSet {ranges}
+            }
+
+        }
+
+        impl<T: Range> $imp for RangeSet<T> {
+
+            type Output = Self;
+
+            fn $method(mut self, rhs: Self) -> Self::Output {
+                self.$method_assign(&rhs);
+                self
+            }
+
+        }
+
+        impl<T: Range> $imp_assign<&RangeSet<T>> for RangeSet<T> {
+
+            fn $method_assign(&mut self, rhs: &RangeSet<T>) { apply(&mut self.ranges, &rhs.ranges, $kernel); }
+
+        }
+
+        impl<T: Range> $imp_assign for RangeSet<T> {
+
+            fn $method_assign(&mut self, rhs: Self) { self.$method_assign(&rhs); }
+
+        }
+
+    };
+}
+
+impl_binop!(BitOr, bitor, BitOrAssign, bitor_assign, or_into);
+impl_binop!(BitAnd, bitand, BitAndAssign, bitand_assign, and_into);
+impl_binop!(Sub, sub, SubAssign, sub_assign, sub_into);
+impl_binop!(BitXor, bitxor, BitXorAssign, bitxor_assign, xor_into);
+
+
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use super::super::types::{MAX_IP, MAX_PORT, MAX_SOCKET};
+    use super::super::types::{
+        Endpoint6, Port, IP, IPv6, Socket, SocketV6, MAX_IP, MAX_IPV6, MAX_PORT, MAX_SOCKET, MAX_SOCKET_V6,
+    };
 
     #[test]
     fn test_operator_port_xor() {
 
         let a: Port = Port::new(0, 1000);
         let b: Port = Port::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Port = Port::new(0, 1000);
         let d: Port = Port::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let op_xor = op_1 ^ op_2;
         assert_eq!(op_xor.ranges.len(), 1);
         assert_eq!(op_xor.ranges[0].begin(), 1100);
         assert_eq!(op_xor.ranges[0].end(), 1499);
-        assert_eq!(op_xor.range_of.unwrap(), TypeRange::Port);
+        assert_eq!(op_xor.ranges[0].range_of(), TypeRange::Port);
 
     }
 
@@ -232,16 +695,16 @@ mod tests {
 
         let a: IP = IP::new(0, 1000);
         let b: IP = IP::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: IP = IP::new(0, 1000);
         let d: IP = IP::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let op_xor = op_1 ^ op_2;
         assert_eq!(op_xor.ranges.len(), 1);
         assert_eq!(op_xor.ranges[0].begin(), 1100);
         assert_eq!(op_xor.ranges[0].end(), 1499);
-        assert_eq!(op_xor.range_of.unwrap(), TypeRange::IP);
+        assert_eq!(op_xor.ranges[0].range_of(), TypeRange::IP);
 
     }
 
@@ -250,16 +713,16 @@ mod tests {
 
         let a: Socket = Socket::new(0, 1000);
         let b: Socket = Socket::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Socket = Socket::new(0, 1000);
         let d: Socket = Socket::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let op_xor = op_1 ^ op_2;
         assert_eq!(op_xor.ranges.len(), 1);
         assert_eq!(op_xor.ranges[0].begin(), 1100);
         assert_eq!(op_xor.ranges[0].end(), 1499);
-        assert_eq!(op_xor.range_of.unwrap(), TypeRange::Socket);
+        assert_eq!(op_xor.ranges[0].range_of(), TypeRange::Socket);
 
     }
 
@@ -268,14 +731,13 @@ mod tests {
 
         let a: Port = Port::new(0, 1000);
         let b: Port = Port::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Port = Port::new(0, 1000);
         let d: Port = Port::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let op_sub = op_1 - op_2;
         assert_eq!(op_sub.ranges.len(), 0);
-        assert_eq!(op_sub.range_of.unwrap(), TypeRange::Port);
 
     }
 
@@ -284,16 +746,16 @@ mod tests {
 
         let a: Port = Port::new(0, 1000);
         let b: Port = Port::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Port = Port::new(0, 1000);
         let d: Port = Port::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let op_sub = op_2 - op_1;
         assert_eq!(op_sub.ranges.len(), 1);
         assert_eq!(op_sub.ranges[0].begin(), 1100);
         assert_eq!(op_sub.ranges[0].end(), 1499);
-        assert_eq!(op_sub.range_of.unwrap(), TypeRange::Port);
+        assert_eq!(op_sub.ranges[0].range_of(), TypeRange::Port);
 
     }
 
@@ -302,14 +764,13 @@ mod tests {
 
         let a: IP = IP::new(0, 1000);
         let b: IP = IP::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: IP = IP::new(0, 1000);
         let d: IP = IP::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let op_sub = op_1 - op_2;
         assert_eq!(op_sub.ranges.len(), 0);
-        assert_eq!(op_sub.range_of.unwrap(), TypeRange::IP);
 
     }
 
@@ -318,16 +779,16 @@ mod tests {
 
         let a: IP = IP::new(0, 1000);
         let b: IP = IP::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: IP = IP::new(0, 1000);
         let d: IP = IP::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let op_sub = op_2 - op_1;
         assert_eq!(op_sub.ranges.len(), 1);
         assert_eq!(op_sub.ranges[0].begin(), 1100);
         assert_eq!(op_sub.ranges[0].end(), 1499);
-        assert_eq!(op_sub.range_of.unwrap(), TypeRange::IP);
+        assert_eq!(op_sub.ranges[0].range_of(), TypeRange::IP);
 
     }
 
@@ -336,14 +797,13 @@ mod tests {
 
         let a: Socket = Socket::new(0, 1000);
         let b: Socket = Socket::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Socket = Socket::new(0, 1000);
         let d: Socket = Socket::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let op_sub = op_1 - op_2;
         assert_eq!(op_sub.ranges.len(), 0);
-        assert_eq!(op_sub.range_of.unwrap(), TypeRange::Socket);
 
     }
 
@@ -352,16 +812,16 @@ mod tests {
 
         let a: Socket = Socket::new(0, 1000);
         let b: Socket = Socket::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Socket = Socket::new(0, 1000);
         let d: Socket = Socket::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let op_sub = op_2 - op_1;
         assert_eq!(op_sub.ranges.len(), 1);
         assert_eq!(op_sub.ranges[0].begin(), 1100);
         assert_eq!(op_sub.ranges[0].end(), 1499);
-        assert_eq!(op_sub.range_of.unwrap(), TypeRange::Socket);
+        assert_eq!(op_sub.ranges[0].range_of(), TypeRange::Socket);
 
     }
 
@@ -370,10 +830,10 @@ mod tests {
 
         let a: Port = Port::new(0, 1000);
         let b: Port = Port::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Port = Port::new(0, 1000);
         let d: Port = Port::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let log_and = op_1 & op_2;
         assert_eq!(log_and.ranges.len(), 2);
@@ -381,7 +841,7 @@ mod tests {
         assert_eq!(log_and.ranges[0].end(), 1000);
         assert_eq!(log_and.ranges[1].begin(), 1500);
         assert_eq!(log_and.ranges[1].end(), 2000);
-        assert_eq!(log_and.range_of.unwrap(), TypeRange::Port);
+        assert_eq!(log_and.ranges[0].range_of(), TypeRange::Port);
 
     }
 
@@ -390,10 +850,10 @@ mod tests {
 
         let a: IP = IP::new(0, 1000);
         let b: IP = IP::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: IP = IP::new(0, 1000);
         let d: IP = IP::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let log_and = op_1 & op_2;
         assert_eq!(log_and.ranges.len(), 2);
@@ -401,7 +861,7 @@ mod tests {
         assert_eq!(log_and.ranges[0].end(), 1000);
         assert_eq!(log_and.ranges[1].begin(), 1500);
         assert_eq!(log_and.ranges[1].end(), 2000);
-        assert_eq!(log_and.range_of.unwrap(), TypeRange::IP);
+        assert_eq!(log_and.ranges[0].range_of(), TypeRange::IP);
 
     }
 
@@ -410,10 +870,10 @@ mod tests {
 
         let a: Socket = Socket::new(0, 1000);
         let b: Socket = Socket::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Socket = Socket::new(0, 1000);
         let d: Socket = Socket::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let log_and = op_1 & op_2;
         assert_eq!(log_and.ranges.len(), 2);
@@ -421,7 +881,7 @@ mod tests {
         assert_eq!(log_and.ranges[0].end(), 1000);
         assert_eq!(log_and.ranges[1].begin(), 1500);
         assert_eq!(log_and.ranges[1].end(), 2000);
-        assert_eq!(log_and.range_of.unwrap(), TypeRange::Socket);
+        assert_eq!(log_and.ranges[0].range_of(), TypeRange::Socket);
 
     }
 
@@ -430,10 +890,10 @@ mod tests {
 
         let a: Port = Port::new(0, 1000);
         let b: Port = Port::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Port = Port::new(0, 1000);
         let d: Port = Port::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let log_or = op_1 | op_2;
         assert_eq!(log_or.ranges.len(), 2);
@@ -441,7 +901,7 @@ mod tests {
         assert_eq!(log_or.ranges[0].end(), 1000);
         assert_eq!(log_or.ranges[1].begin(), 1100);
         assert_eq!(log_or.ranges[1].end(), 2000);
-        assert_eq!(log_or.range_of.unwrap(), TypeRange::Port);
+        assert_eq!(log_or.ranges[0].range_of(), TypeRange::Port);
 
     }
 
@@ -450,10 +910,10 @@ mod tests {
 
         let a: IP = IP::new(0, 1000);
         let b: IP = IP::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: IP = IP::new(0, 1000);
         let d: IP = IP::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let log_or = op_1 | op_2;
         assert_eq!(log_or.ranges.len(), 2);
@@ -461,7 +921,7 @@ mod tests {
         assert_eq!(log_or.ranges[0].end(), 1000);
         assert_eq!(log_or.ranges[1].begin(), 1100);
         assert_eq!(log_or.ranges[1].end(), 2000);
-        assert_eq!(log_or.range_of.unwrap(), TypeRange::IP);
+        assert_eq!(log_or.ranges[0].range_of(), TypeRange::IP);
 
     }
 
@@ -470,10 +930,10 @@ mod tests {
 
         let a: Socket = Socket::new(0, 1000);
         let b: Socket = Socket::new(1500, 2000);
-        let op_1 = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
+        let op_1 = RangeSet::from_ranges(vec![a, b]).unwrap();
         let c: Socket = Socket::new(0, 1000);
         let d: Socket = Socket::new(1100, 2000);
-        let op_2 = Op {ranges: vec![Box::new(c), Box::new(d)], range_of: None};
+        let op_2 = RangeSet::from_ranges(vec![c, d]).unwrap();
 
         let log_or = op_1 | op_2;
         assert_eq!(log_or.ranges.len(), 2);
@@ -481,7 +941,7 @@ mod tests {
         assert_eq!(log_or.ranges[0].end(), 1000);
         assert_eq!(log_or.ranges[1].begin(), 1100);
         assert_eq!(log_or.ranges[1].end(), 2000);
-        assert_eq!(log_or.range_of.unwrap(), TypeRange::Socket);
+        assert_eq!(log_or.ranges[0].range_of(), TypeRange::Socket);
 
     }
 
@@ -491,15 +951,14 @@ mod tests {
         let a: Port = Port::new(0, 2);
         let b: Port = Port::new(1, 3);
 
-        let op = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
-        assert_eq!(op.ranges.len(), 2);
-        assert_eq!(op.range_of, None);
+        let op = RangeSet::from_ranges(vec![a, b]).unwrap();
+        assert_eq!(op.ranges.len(), 1);
 
         let log_inv = !op;
         assert_eq!(log_inv.ranges.len(), 1);
         assert_eq!(log_inv.ranges[0].begin(), 4);
         assert_eq!(log_inv.ranges[0].end(), MAX_PORT);
-        assert_eq!(log_inv.range_of.unwrap(), TypeRange::Port);
+        assert_eq!(log_inv.ranges[0].range_of(), TypeRange::Port);
 
     }
 
@@ -510,15 +969,14 @@ mod tests {
         let b: Port = Port::new(15, 30);
         let c: Port = Port::new(20, 40);
 
-        let op = Op {ranges: vec![Box::new(a), Box::new(b), Box::new(c)], range_of: None};
-        assert_eq!(op.ranges.len(), 3);
-        assert_eq!(op.range_of, None);
+        let op = RangeSet::from_ranges(vec![a, b, c]).unwrap();
+        assert_eq!(op.ranges.len(), 1);
 
         let log_inv = !op;
         assert_eq!(log_inv.ranges.len(), 2);
         assert_eq!(log_inv.ranges[0].begin(), 0);
         assert_eq!(log_inv.ranges[0].end(), 9);
-        assert_eq!(log_inv.range_of.unwrap(), TypeRange::Port);
+        assert_eq!(log_inv.ranges[0].range_of(), TypeRange::Port);
 
     }
 
@@ -528,15 +986,14 @@ mod tests {
         let a: IP = IP::new(0, 2);
         let b: IP = IP::new(1, 3);
 
-        let op = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
-        assert_eq!(op.ranges.len(), 2);
-        assert_eq!(op.range_of, None);
+        let op = RangeSet::from_ranges(vec![a, b]).unwrap();
+        assert_eq!(op.ranges.len(), 1);
 
         let log_inv = !op;
         assert_eq!(log_inv.ranges.len(), 1);
         assert_eq!(log_inv.ranges[0].begin(), 4);
         assert_eq!(log_inv.ranges[0].end(), MAX_IP);
-        assert_eq!(log_inv.range_of.unwrap(), TypeRange::IP);
+        assert_eq!(log_inv.ranges[0].range_of(), TypeRange::IP);
 
     }
 
@@ -547,15 +1004,14 @@ mod tests {
         let b: IP = IP::new(15, 30);
         let c: IP = IP::new(20, 40);
 
-        let op = Op {ranges: vec![Box::new(a), Box::new(b), Box::new(c)], range_of: None};
-        assert_eq!(op.ranges.len(), 3);
-        assert_eq!(op.range_of, None);
+        let op = RangeSet::from_ranges(vec![a, b, c]).unwrap();
+        assert_eq!(op.ranges.len(), 1);
 
         let log_inv = !op;
         assert_eq!(log_inv.ranges.len(), 2);
         assert_eq!(log_inv.ranges[0].begin(), 0);
         assert_eq!(log_inv.ranges[0].end(), 9);
-        assert_eq!(log_inv.range_of.unwrap(), TypeRange::IP);
+        assert_eq!(log_inv.ranges[0].range_of(), TypeRange::IP);
 
     }
 
@@ -565,15 +1021,14 @@ mod tests {
         let a: Socket = Socket::new(0, 2);
         let b: Socket = Socket::new(1, 3);
 
-        let op = Op {ranges: vec![Box::new(a), Box::new(b)], range_of: None};
-        assert_eq!(op.ranges.len(), 2);
-        assert_eq!(op.range_of, None);
+        let op = RangeSet::from_ranges(vec![a, b]).unwrap();
+        assert_eq!(op.ranges.len(), 1);
 
         let log_inv = !op;
         assert_eq!(log_inv.ranges.len(), 1);
         assert_eq!(log_inv.ranges[0].begin(), 4);
         assert_eq!(log_inv.ranges[0].end(), MAX_SOCKET);
-        assert_eq!(log_inv.range_of.unwrap(), TypeRange::Socket);
+        assert_eq!(log_inv.ranges[0].range_of(), TypeRange::Socket);
 
     }
 
@@ -584,15 +1039,579 @@ mod tests {
         let b: Socket = Socket::new(15, 30);
         let c: Socket = Socket::new(20, 40);
 
-        let op = Op {ranges: vec![Box::new(a), Box::new(b), Box::new(c)], range_of: None};
-        assert_eq!(op.ranges.len(), 3);
-        assert_eq!(op.range_of, None);
+        let op = RangeSet::from_ranges(vec![a, b, c]).unwrap();
+        assert_eq!(op.ranges.len(), 1);
 
         let log_inv = !op;
         assert_eq!(log_inv.ranges.len(), 2);
         assert_eq!(log_inv.ranges[0].begin(), 0);
         assert_eq!(log_inv.ranges[0].end(), 9);
-        assert_eq!(log_inv.range_of.unwrap(), TypeRange::Socket);
+        assert_eq!(log_inv.ranges[0].range_of(), TypeRange::Socket);
+
+    }
+
+    #[test]
+    fn test_operator_port_empty() {
+
+        let empty: RangeSet<Port> = RangeSet::empty();
+        let op = RangeSet::from_ranges(vec![Port::new(0, 1000), Port::new(1500, 2000)]).unwrap();
+
+        assert!(empty.is_empty());
+        assert!((!empty.clone()).is_full());
+        assert_eq!((op.clone() | empty.clone()).ranges, op.ranges);
+        assert_eq!((empty.clone() | op.clone()).ranges, op.ranges);
+        assert!((empty.clone() | empty.clone()).is_empty());
+        assert!((op.clone() & empty.clone()).is_empty());
+        assert!((empty.clone() & op.clone()).is_empty());
+        assert_eq!((op.clone() - empty.clone()).ranges, op.ranges);
+        assert!((empty.clone() - op.clone()).is_empty());
+        assert_eq!((op.clone() ^ empty.clone()).ranges, op.ranges);
+        assert_eq!((empty.clone() ^ op.clone()).ranges, op.ranges);
+        assert!((op.clone() - op.clone()).is_empty());
+
+    }
+
+    #[test]
+    fn test_operator_port_full() {
+
+        let full: RangeSet<Port> = RangeSet::full();
+        let op = RangeSet::from_ranges(vec![Port::new(0, 1000), Port::new(1500, 2000)]).unwrap();
+
+        assert!(full.is_full());
+        assert_eq!(full.ranges, vec![Port::new(0, MAX_PORT)]);
+        assert!((!full.clone()).is_empty());
+        assert!((op.clone() | full.clone()).is_full());
+        assert!((full.clone() | op.clone()).is_full());
+        assert_eq!((op.clone() & full.clone()).ranges, op.ranges);
+        assert_eq!((full.clone() & op.clone()).ranges, op.ranges);
+        assert!((op.clone() - full.clone()).is_empty());
+        assert_eq!((full.clone() - op.clone()).ranges, (!op.clone()).ranges);
+        assert_eq!((op.clone() ^ full.clone()).ranges, (!op.clone()).ranges);
+        assert!((full.clone() ^ full.clone()).is_empty());
+        assert!((op.clone() | !op.clone()).is_full());
+
+    }
+
+    #[test]
+    fn test_operator_ip_empty() {
+
+        let empty: RangeSet<IP> = RangeSet::empty();
+        let op = RangeSet::from_ranges(vec![IP::new(0, 1000), IP::new(1500, 2000)]).unwrap();
+
+        assert!(empty.is_empty());
+        assert!((!empty.clone()).is_full());
+        assert_eq!((op.clone() | empty.clone()).ranges, op.ranges);
+        assert_eq!((empty.clone() | op.clone()).ranges, op.ranges);
+        assert!((empty.clone() | empty.clone()).is_empty());
+        assert!((op.clone() & empty.clone()).is_empty());
+        assert!((empty.clone() & op.clone()).is_empty());
+        assert_eq!((op.clone() - empty.clone()).ranges, op.ranges);
+        assert!((empty.clone() - op.clone()).is_empty());
+        assert_eq!((op.clone() ^ empty.clone()).ranges, op.ranges);
+        assert_eq!((empty.clone() ^ op.clone()).ranges, op.ranges);
+        assert!((op.clone() - op.clone()).is_empty());
+
+    }
+
+    #[test]
+    fn test_operator_ip_full() {
+
+        let full: RangeSet<IP> = RangeSet::full();
+        let op = RangeSet::from_ranges(vec![IP::new(0, 1000), IP::new(1500, 2000)]).unwrap();
+
+        assert!(full.is_full());
+        assert_eq!(full.ranges, vec![IP::new(0, MAX_IP)]);
+        assert!((!full.clone()).is_empty());
+        assert!((op.clone() | full.clone()).is_full());
+        assert!((full.clone() | op.clone()).is_full());
+        assert_eq!((op.clone() & full.clone()).ranges, op.ranges);
+        assert_eq!((full.clone() & op.clone()).ranges, op.ranges);
+        assert!((op.clone() - full.clone()).is_empty());
+        assert_eq!((full.clone() - op.clone()).ranges, (!op.clone()).ranges);
+        assert_eq!((op.clone() ^ full.clone()).ranges, (!op.clone()).ranges);
+        assert!((full.clone() ^ full.clone()).is_empty());
+        assert!((op.clone() | !op.clone()).is_full());
+
+    }
+
+    #[test]
+    fn test_operator_socket_empty() {
+
+        let empty: RangeSet<Socket> = RangeSet::empty();
+        let op = RangeSet::from_ranges(vec![Socket::new(0, 1000), Socket::new(1500, 2000)]).unwrap();
+
+        assert!(empty.is_empty());
+        assert!((!empty.clone()).is_full());
+        assert_eq!((op.clone() | empty.clone()).ranges, op.ranges);
+        assert_eq!((empty.clone() | op.clone()).ranges, op.ranges);
+        assert!((empty.clone() | empty.clone()).is_empty());
+        assert!((op.clone() & empty.clone()).is_empty());
+        assert!((empty.clone() & op.clone()).is_empty());
+        assert_eq!((op.clone() - empty.clone()).ranges, op.ranges);
+        assert!((empty.clone() - op.clone()).is_empty());
+        assert_eq!((op.clone() ^ empty.clone()).ranges, op.ranges);
+        assert_eq!((empty.clone() ^ op.clone()).ranges, op.ranges);
+        assert!((op.clone() - op.clone()).is_empty());
+
+    }
+
+    #[test]
+    fn test_operator_socket_full() {
+
+        let full: RangeSet<Socket> = RangeSet::full();
+        let op = RangeSet::from_ranges(vec![Socket::new(0, 1000), Socket::new(1500, 2000)]).unwrap();
+
+        assert!(full.is_full());
+        assert_eq!(full.ranges, vec![Socket::new(0, MAX_SOCKET)]);
+        assert!((!full.clone()).is_empty());
+        assert!((op.clone() | full.clone()).is_full());
+        assert!((full.clone() | op.clone()).is_full());
+        assert_eq!((op.clone() & full.clone()).ranges, op.ranges);
+        assert_eq!((full.clone() & op.clone()).ranges, op.ranges);
+        assert!((op.clone() - full.clone()).is_empty());
+        assert_eq!((full.clone() - op.clone()).ranges, (!op.clone()).ranges);
+        assert_eq!((op.clone() ^ full.clone()).ranges, (!op.clone()).ranges);
+        assert!((full.clone() ^ full.clone()).is_empty());
+        assert!((op.clone() | !op.clone()).is_full());
+
+    }
+
+    #[test]
+    fn test_from_ranges_normalizes() {
+
+        let op = RangeSet::from_ranges(vec![
+            Port::new(30, 40),
+            Port::new(0, 10),
+            Port::new(5, 20),
+            Port::new(21, 25),
+            Port::new(27, 29),
+            Port::new(50, 60),
+        ]).unwrap();
+
+        assert_eq!(op.ranges(), &[Port::new(0, 25), Port::new(27, 40), Port::new(50, 60)]);
+
+    }
+
+    #[test]
+    fn test_from_ranges_rejects_inverted() {
+
+        let op = RangeSet::from_ranges(vec![IP::new(0, 10), IP::new(20, 15)]);
+        assert_eq!(op.unwrap_err(), RangeError::Inverted { begin: 20, end: 15 });
+
+    }
+
+    #[test]
+    fn test_from_ranges_rejects_out_of_bounds() {
+
+        let socket = RangeSet::from_ranges(vec![Socket::new(0, MAX_SOCKET + 1)]);
+        assert_eq!(socket.unwrap_err(), RangeError::OutOfBounds { value: MAX_SOCKET + 1, max: MAX_SOCKET });
+
+    }
+
+    #[test]
+    fn test_operator_unsorted_input() {
+
+        let op_1 = RangeSet::from_ranges(vec![Socket::new(1500, 2000), Socket::new(0, 1000)]).unwrap();
+        let op_2 = RangeSet::from_ranges(vec![Socket::new(1001, 1499), Socket::new(10, 20)]).unwrap();
+
+        assert_eq!((!op_1.clone()).ranges(), &[Socket::new(1001, 1499), Socket::new(2001, MAX_SOCKET)]);
+        assert_eq!((op_1.clone() | op_2.clone()).ranges(), &[Socket::new(0, 2000)]);
+        assert_eq!((op_1.clone() & op_2.clone()).ranges(), &[Socket::new(10, 20)]);
+        assert_eq!((op_2.clone() - op_1.clone()).ranges(), &[Socket::new(1001, 1499)]);
+        assert_eq!(
+            (op_1.clone() ^ op_2.clone()).ranges(),
+            &[Socket::new(0, 9), Socket::new(21, 2000)]
+        );
+
+    }
+
+    #[test]
+    fn test_contains() {
+
+        let op = RangeSet::from_ranges(vec![Port::new(10, 20), Port::new(30, 40), Port::new(MAX_PORT, MAX_PORT)]).unwrap();
+
+        assert!(!op.contains(0));
+        assert!(!op.contains(9));
+        assert!(op.contains(10));
+        assert!(op.contains(15));
+        assert!(op.contains(20));
+        assert!(!op.contains(21));
+        assert!(!op.contains(29));
+        assert!(op.contains(40));
+        assert!(!op.contains(41));
+        assert!(op.contains(MAX_PORT));
+        assert!(!RangeSet::<Port>::empty().contains(0));
+        assert!(RangeSet::<Socket>::full().contains(MAX_SOCKET));
+
+    }
+
+    #[test]
+    fn test_contains_range() {
+
+        let op = RangeSet::from_ranges(vec![IP::new(10, 20), IP::new(30, 40)]).unwrap();
+
+        assert!(op.contains_range(10, 20));
+        assert!(op.contains_range(12, 18));
+        assert!(op.contains_range(30, 30));
+        assert!(!op.contains_range(5, 15));
+        assert!(!op.contains_range(15, 35));
+        assert!(!op.contains_range(21, 29));
+        assert!(!op.contains_range(20, 10));
+        assert!(!op.contains_range(41, 50));
+
+    }
+
+    #[test]
+    fn test_subset_superset() {
+
+        let big = RangeSet::from_ranges(vec![Socket::new(0, 100), Socket::new(200, 300)]).unwrap();
+        let small = RangeSet::from_ranges(vec![Socket::new(10, 20), Socket::new(250, 300)]).unwrap();
+        let other = RangeSet::from_ranges(vec![Socket::new(10, 20), Socket::new(150, 160)]).unwrap();
+        let empty: RangeSet<Socket> = RangeSet::empty();
+
+        assert!(small.is_subset(&big));
+        assert!(big.is_superset(&small));
+        assert!(!big.is_subset(&small));
+        assert!(!other.is_subset(&big));
+        assert!(big.is_subset(&big));
+        assert!(empty.is_subset(&big));
+        assert!(big.is_subset(&RangeSet::full()));
+
+    }
+
+    #[test]
+    fn test_disjoint_overlaps() {
+
+        let op_1 = RangeSet::from_ranges(vec![Port::new(0, 100), Port::new(200, 300)]).unwrap();
+        let op_2 = RangeSet::from_ranges(vec![Port::new(101, 199), Port::new(301, 400)]).unwrap();
+        let op_3 = RangeSet::from_ranges(vec![Port::new(150, 200)]).unwrap();
+
+        assert!(op_1.is_disjoint(&op_2));
+        assert!(!op_1.overlaps(&op_2));
+        assert!(op_1.overlaps(&op_3));
+        assert!(op_2.overlaps(&op_3));
+        assert!(op_1.is_disjoint(&RangeSet::empty()));
+
+    }
+
+    #[test]
+    fn test_equality() {
+
+        let op_1 = RangeSet::from_ranges(vec![IP::new(0, 10), IP::new(11, 20)]).unwrap();
+        let op_2 = RangeSet::from_ranges(vec![IP::new(5, 20), IP::new(0, 4)]).unwrap();
+
+        assert_eq!(op_1, op_2);
+        assert_ne!(op_1, RangeSet::from_ranges(vec![IP::new(0, 19)]).unwrap());
+        assert_eq!(!RangeSet::<IP>::empty(), RangeSet::full());
+
+    }
+
+    #[test]
+    fn test_statistics() {
+
+        let op = RangeSet::from_ranges(vec![Port::new(0, 9), Port::new(20, 119), Port::new(200, 209)]).unwrap();
+
+        assert_eq!(op.count_ranges(), 3);
+        assert_eq!(op.len_values(), Some(120));
+        assert_eq!(op.largest_range(), Some(Port::new(20, 119)));
+        assert_eq!(op.smallest_range(), Some(Port::new(0, 9)));
+        assert_eq!(op.coverage(), 120.0 / 65536.0);
+
+    }
+
+    #[test]
+    fn test_statistics_empty_and_full() {
+
+        let empty: RangeSet<IP> = RangeSet::empty();
+        assert_eq!(empty.count_ranges(), 0);
+        assert_eq!(empty.len_values(), Some(0));
+        assert_eq!(empty.largest_range(), None);
+        assert_eq!(empty.smallest_range(), None);
+        assert_eq!(empty.coverage(), 0.0);
+
+        assert_eq!(RangeSet::<Port>::full().len_values(), Some(1 << 16));
+        assert_eq!(RangeSet::<IP>::full().len_values(), Some(1 << 32));
+        assert_eq!(RangeSet::<Socket>::full().len_values(), Some(1 << 48));
+        assert_eq!(RangeSet::<Socket>::full().coverage(), 1.0);
+
+    }
+
+    #[test]
+    fn test_operator_borrowed() {
+
+        let op_1 = RangeSet::from_ranges(vec![IP::new(0, 1000), IP::new(1500, 2000)]).unwrap();
+        let op_2 = RangeSet::from_ranges(vec![IP::new(0, 1000), IP::new(1100, 2000)]).unwrap();
+
+        assert_eq!(!&op_1, !op_1.clone());
+        assert_eq!(&op_1 | &op_2, op_1.clone() | op_2.clone());
+        assert_eq!(&op_1 & &op_2, op_1.clone() & op_2.clone());
+        assert_eq!(&op_1 - &op_2, op_1.clone() - op_2.clone());
+        assert_eq!(&op_2 - &op_1, op_2.clone() - op_1.clone());
+        assert_eq!(&op_1 ^ &op_2, op_1.clone() ^ op_2.clone());
+        assert_eq!((&op_1 ^ &op_2).ranges(), &[IP::new(1100, 1499)]);
+
+    }
+
+    #[test]
+    fn test_operator_assign() {
+
+        let op_1 = RangeSet::from_ranges(vec![Port::new(0, 1000), Port::new(1500, 2000)]).unwrap();
+        let op_2 = RangeSet::from_ranges(vec![Port::new(0, 1000), Port::new(1100, 2000)]).unwrap();
+
+        let mut op = op_1.clone();
+        op |= &op_2;
+        assert_eq!(op, &op_1 | &op_2);
+
+        let mut op = op_1.clone();
+        op &= &op_2;
+        assert_eq!(op, &op_1 & &op_2);
+
+        let mut op = op_2.clone();
+        op -= &op_1;
+        assert_eq!(op, &op_2 - &op_1);
+
+        let mut op = op_1.clone();
+        op ^= op_2.clone();
+        assert_eq!(op, &op_1 ^ &op_2);
+
+        let mut op: RangeSet<Port> = RangeSet::empty();
+        op |= RangeSet::full();
+        op -= &op_1;
+        assert_eq!(op, !&op_1);
+
+    }
+
+    #[test]
+    fn test_sorted_streams() {
+
+        let op = RangeSet::from_ranges(vec![Port::new(0, 100), Port::new(500, 1000), Port::new(2000, 2000)]).unwrap();
+        let stream = vec![Port::new(50, 60), Port::new(55, 200), Port::new(201, 300), Port::new(900, 2500)];
+        let other = RangeSet::from_ranges(stream.clone()).unwrap();
+
+        assert_eq!(RangeSet::from_sorted_iter(stream.clone()), other);
+        assert_eq!(op.union_sorted(stream.clone()), &op | &other);
+        assert_eq!(op.intersect_sorted(stream.clone()), &op & &other);
+
+        assert_eq!(op.union_sorted(vec![]), op);
+        assert!(op.intersect_sorted(vec![]).is_empty());
+        assert_eq!(RangeSet::<Port>::empty().union_sorted(stream), other);
+        assert_eq!(RangeSet::<Port>::full().intersect_sorted(vec![Port::new(0, MAX_PORT)]), RangeSet::full());
+
+    }
+
+    #[test]
+    fn test_operator_assign_reuses_buffer() {
+
+        let op_2 = RangeSet::from_ranges(vec![Port::new(50, 60), Port::new(900, 1200)]).unwrap();
+
+        let mut op = RangeSet::from_ranges(vec![Port::new(0, 100), Port::new(500, 1000)]).unwrap();
+        op.ranges.reserve(16);
+        let buffer = op.ranges.as_ptr();
+
+        op -= &op_2;
+        op ^= &op_2;
+        op &= &op_2;
+        op |= &op_2;
+        let op = op | op_2.clone();
+
+        assert_eq!(op.ranges.as_ptr(), buffer);
+        assert_eq!(op, op_2);
+
+    }
+
+    // Reference implementations over the complement, used to check the single-pass merges.
+    fn slow_sub(a: &RangeSet<Port>, b: &RangeSet<Port>) -> RangeSet<Port> { a & &!b }
+
+    fn slow_xor(a: &RangeSet<Port>, b: &RangeSet<Port>) -> RangeSet<Port> {
+        slow_sub(a, b) | slow_sub(b, a)
+    }
+
+    #[test]
+    fn test_operator_sub_xor_match_reference() {
+
+        let sets = vec![
+            RangeSet::empty(),
+            RangeSet::full(),
+            RangeSet::from_ranges(vec![Port::new(0, 10)]).unwrap(),
+            RangeSet::from_ranges(vec![Port::new(5, 10), Port::new(20, 30)]).unwrap(),
+            RangeSet::from_ranges(vec![Port::new(0, 4), Port::new(11, 19), Port::new(31, MAX_PORT)]).unwrap(),
+            RangeSet::from_ranges(vec![Port::new(10, 20), Port::new(25, 25), Port::new(30, 40)]).unwrap(),
+            RangeSet::from_ranges(vec![Port::new(1, 1), Port::new(3, 3), Port::new(MAX_PORT, MAX_PORT)]).unwrap(),
+            RangeSet::from_ranges(vec![Port::new(0, 2), Port::new(4, 100), Port::new(1000, 2000)]).unwrap(),
+        ];
+
+        for a in &sets {
+            for b in &sets {
+                assert_eq!(a - b, slow_sub(a, b), "{a} - {b}");
+                assert_eq!(a ^ b, slow_xor(a, b), "{a} ^ {b}");
+                assert_eq!(a ^ b, b ^ a, "{a} ^ {b}");
+            }
+        }
+
+    }
+
+    #[test]
+    fn test_operator_or_merge() {
+
+        let op_1 = RangeSet::from_ranges(vec![Socket::new(0, 10), Socket::new(20, 30), Socket::new(50, 60)]).unwrap();
+        let op_2 = RangeSet::from_ranges(vec![Socket::new(5, 19), Socket::new(31, 40), Socket::new(70, 80)]).unwrap();
+
+        assert_eq!((&op_1 | &op_2).ranges(), &[Socket::new(0, 40), Socket::new(50, 60), Socket::new(70, 80)]);
+        assert_eq!(&op_1 | &op_2, &op_2 | &op_1);
+        assert_eq!(&op_1 | &op_1, op_1);
+
+        let raw = vec![
+            Socket::new(0, 10), Socket::new(20, 30), Socket::new(50, 60),
+            Socket::new(5, 19), Socket::new(31, 40), Socket::new(70, 80),
+        ];
+        assert_eq!(&op_1 | &op_2, RangeSet::from_ranges(raw).unwrap());
+
+    }
+
+    #[test]
+    fn test_union_intersect_all() {
+
+        let sets = vec![
+            RangeSet::from_ranges(vec![IP::new(0, 100), IP::new(200, 300)]).unwrap(),
+            RangeSet::from_ranges(vec![IP::new(50, 250)]).unwrap(),
+            RangeSet::from_ranges(vec![IP::new(90, 210), IP::new(301, 400)]).unwrap(),
+        ];
+
+        let union = RangeSet::union_all(&sets);
+        assert_eq!(union, sets.iter().fold(RangeSet::empty(), |acc, x| acc | x.clone()));
+        assert_eq!(union.ranges(), &[IP::new(0, 400)]);
+
+        let intersection = RangeSet::intersect_all(&sets);
+        assert_eq!(intersection, sets.iter().fold(RangeSet::full(), |acc, x| acc & x.clone()));
+        assert_eq!(intersection.ranges(), &[IP::new(90, 100), IP::new(200, 210)]);
+
+        assert!(RangeSet::<IP>::union_all([]).is_empty());
+        assert!(RangeSet::<IP>::intersect_all([]).is_full());
+
+    }
+
+    #[test]
+    fn test_covered_by_at_least() {
+
+        let sets = vec![
+            RangeSet::from_ranges(vec![Port::new(0, 10), Port::new(20, 30)]).unwrap(),
+            RangeSet::from_ranges(vec![Port::new(5, 25)]).unwrap(),
+            RangeSet::from_ranges(vec![Port::new(8, 9), Port::new(26, MAX_PORT)]).unwrap(),
+        ];
+
+        let twice = RangeSet::covered_by_at_least(2, &sets);
+        assert_eq!(twice.ranges(), &[Port::new(5, 10), Port::new(20, 30)]);
+
+        let thrice = RangeSet::covered_by_at_least(3, &sets);
+        assert_eq!(thrice.ranges(), &[Port::new(8, 9)]);
+
+        assert!(RangeSet::covered_by_at_least(0, &sets).is_full());
+        assert!(RangeSet::covered_by_at_least(4, &sets).is_empty());
+
+    }
+
+    #[test]
+    fn test_collect_and_extend() {
+
+        let op: RangeSet<Socket> = (0..10)
+            .map(|x| RangeSet::from_ranges(vec![Socket::new(x * 10, x * 10 + 5)]).unwrap())
+            .collect();
+        assert_eq!(op.count_ranges(), 10);
+        assert_eq!(op.len_values(), Some(60));
+
+        let mut op = op;
+        op.extend((0..10).map(|x| RangeSet::from_ranges(vec![Socket::new(x * 10 + 6, x * 10 + 9)]).unwrap()));
+        assert_eq!(op.ranges(), &[Socket::new(0, 99)]);
+
+    }
+
+    #[test]
+    fn test_from_tagged() {
+
+        let op: RangeSet<Port> = RangeSet::from_tagged(vec![
+            (TypeRange::Port, 443, 443),
+            (TypeRange::Port, 80, 80),
+        ]).unwrap();
+        assert_eq!(op.ranges(), &[Port::new(80, 80), Port::new(443, 443)]);
+
+        let mixed: std::result::Result<RangeSet<Port>, RangeError<u16>> = RangeSet::from_tagged(vec![
+            (TypeRange::Port, 80, 80),
+            (TypeRange::IP, 0, 100),
+        ]);
+        assert_eq!(mixed.unwrap_err(), RangeError::TypeMismatch { expected: TypeRange::Port, found: TypeRange::IP });
+
+        let invalid: std::result::Result<RangeSet<IP>, RangeError<u32>> = RangeSet::from_tagged(vec![(TypeRange::IP, 10, 3)]);
+        assert_eq!(invalid.unwrap_err(), RangeError::Inverted { begin: 10, end: 3 });
+
+    }
+
+    #[test]
+    fn test_display() {
+
+        let op = RangeSet::from_ranges(vec![Port::new(0, 1000), Port::new(1500, 1500)]).unwrap();
+
+        assert_eq!(format!("{}", op), "[0-1000, 1500]");
+        assert_eq!(format!("{:#}", op), "[(0, 1000), (1500, 1500)]");
+        assert_eq!(format!("{}", RangeSet::<IP>::empty()), "[]");
+        assert_eq!(format!("{}", RangeSet::<IP>::full()), "[0.0.0.0/0]");
+
+    }
+
+    #[test]
+    fn test_operator_ipv6() {
+
+        let op_1 = RangeSet::from_ranges(vec![IPv6::new(0, 1000), IPv6::new(1500, MAX_IPV6)]).unwrap();
+        let op_2 = RangeSet::from_ranges(vec![IPv6::new(0, 1000), IPv6::new(1100, 2000)]).unwrap();
+
+        assert_eq!((!&op_1).ranges(), &[IPv6::new(1001, 1499)]);
+        assert_eq!((&op_1 | &op_2).ranges(), &[IPv6::new(0, 1000), IPv6::new(1100, MAX_IPV6)]);
+        assert_eq!((&op_1 & &op_2).ranges(), &[IPv6::new(0, 1000), IPv6::new(1500, 2000)]);
+        assert_eq!((&op_1 - &op_2).ranges(), &[IPv6::new(2001, MAX_IPV6)]);
+        assert_eq!((&op_2 - &op_1).ranges(), &[IPv6::new(1100, 1499)]);
+        assert_eq!((&op_1 ^ &op_2).ranges(), &[IPv6::new(1100, 1499), IPv6::new(2001, MAX_IPV6)]);
+        assert!((&op_1 | &!&op_1).is_full());
+        assert!((!RangeSet::<IPv6>::full()).is_empty());
+
+        assert_eq!(RangeSet::<IPv6>::full().len_values(), None);
+        assert_eq!(op_2.len_values(), Some(1902));
+        assert_eq!(RangeSet::<IPv6>::full().coverage(), 1.0);
+        assert!(op_1.contains(MAX_IPV6));
+        assert!(!op_1.contains(1001));
+
+        let sets = [op_1.clone(), op_2.clone()];
+        assert_eq!(RangeSet::union_all(&sets), &op_1 | &op_2);
+        assert_eq!(RangeSet::intersect_all(&sets), &op_1 & &op_2);
+
+    }
+
+    #[test]
+    fn test_operator_socket_v6() {
+
+        let socket = |ip: u128, port: u16| Endpoint6::new(ip, port);
+
+        let op_1 = RangeSet::from_ranges(vec![
+            SocketV6::new(socket(1, 0), socket(1, 1000)),
+            SocketV6::new(socket(1, 1001), socket(2, 10)),
+        ]).unwrap();
+        assert_eq!(op_1.ranges(), &[SocketV6::new(socket(1, 0), socket(2, 10))]);
+
+        let op_2 = RangeSet::from_ranges(vec![SocketV6::new(socket(1, u16::MAX), MAX_SOCKET_V6)]).unwrap();
+
+        assert_eq!(
+            (!&op_1).ranges(),
+            &[
+                SocketV6::new(Endpoint6::ZERO, socket(0, u16::MAX)),
+                SocketV6::new(socket(2, 11), MAX_SOCKET_V6),
+            ]
+        );
+        assert_eq!((&op_1 & &op_2).ranges(), &[SocketV6::new(socket(1, u16::MAX), socket(2, 10))]);
+        assert_eq!((&op_1 - &op_2).ranges(), &[SocketV6::new(socket(1, 0), socket(1, u16::MAX - 1))]);
+        assert_eq!(&op_1 ^ &op_2, (&op_1 | &op_2) - (&op_1 & &op_2));
+        assert!((&op_1 | &!&op_1).is_full());
+
+        assert_eq!(op_1.len_values(), Some(65536 + 11));
+        assert_eq!(RangeSet::<SocketV6>::full().len_values(), None);
+        assert!(op_2.contains(MAX_SOCKET_V6));
 
     }
 
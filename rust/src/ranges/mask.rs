@@ -1,56 +1,199 @@
 
-use crate::ranges::types::{Port, IP, Socket, TypeRange, Range};
-use crate::ranges::logic::{Op};
+use std::fmt;
 
+use crate::ranges::error::RangeError;
+use crate::ranges::types::{convert, Port, Protocol, IP, Socket, MAX_PORT};
+use crate::ranges::logic::RangeSet;
+use crate::ranges::protocol_map::ProtocolMap;
 
-#[derive(Clone)]
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Mask {
     pub range_ips: Vec<IP>,
     pub range_ports: Vec<Port>,
+    pub range_protocols: Vec<Protocol>,
 }
 
 
 
-pub fn convert(a: u64, b: u64) -> u64 {
+// `[10.0.0.0/8, 192.168.0.1] x [80, 443] x [tcp]`
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |items: Vec<String>| items.join(", ");
+        write!(
+            f,
+            "[{}] x [{}] x [{}]",
+            join(self.range_ips.iter().map(|x| x.to_string()).collect()),
+            join(self.range_ports.iter().map(|x| x.to_string()).collect()),
+            join(self.range_protocols.iter().map(|x| x.to_string()).collect()),
+        )
+    }
+}
+
+
+impl Mask {
+
+    // A mask for every protocol, see `with_protocols` to narrow it.
+    pub fn new(range_ips: Vec<IP>, range_ports: Vec<Port>) -> Self {
+        Self { range_ips, range_ports, range_protocols: vec![Protocol::ANY] }
+    }
+
+    pub fn with_protocols(mut self, range_protocols: Vec<Protocol>) -> Self {
+        self.range_protocols = range_protocols;
+        self
+    }
+
+    pub fn try_new(range_ips: Vec<IP>, range_ports: Vec<Port>, range_protocols: Vec<Protocol>) -> Result<Self, RangeError> {
+        let mask = Self { range_ips, range_ports, range_protocols };
+        mask.validate()?;
+        Ok(mask)
+    }
+
+    // Rejects inverted ranges, for masks built with `new` or field by field.
+    pub fn validate(&self) -> Result<(), RangeError> {
+
+        for ip in &self.range_ips {
+            IP::try_new(ip.begin, ip.end).map_err(|e| e.map_bound(u64::from))?;
+        }
+        for port in &self.range_ports {
+            Port::try_new(port.begin, port.end).map_err(|e| e.map_bound(u64::from))?;
+        }
+        for protocol in &self.range_protocols {
+            Protocol::try_new(protocol.begin, protocol.end).map_err(|e| e.map_bound(u64::from))?;
+        }
+
+        Ok(())
+
+    }
+
+    // Checks the IP and port ranges directly, without expanding to sockets.
+    pub fn contains(&self, protocol: u8, ip: u32, port: u16) -> bool {
+        self.range_protocols.iter().any(|x| x.begin <= protocol && protocol <= x.end)
+            && self.range_ips.iter().any(|x| x.begin <= ip && ip <= x.end)
+            && self.range_ports.iter().any(|x| x.begin <= port && port <= x.end)
+    }
+
+    // Lazily yields the canonical sockets of the mask in ascending order. Sockets
+    // carry no protocol, see `ProtocolMap` for a collapsed form per protocol.
+    pub fn sockets(&self) -> Sockets {
+
+        let ips = RangeSet::from_valid_ranges(self.range_ips.clone());
+        let ports = RangeSet::from_valid_ranges(self.range_ports.clone());
+        Sockets::new(ips, ports)
+    }
+
+    // The sockets of the mask for every protocol it covers.
+    pub fn to_collapsed(&self) -> ProtocolMap<RangeSet<Socket>> {
+        ProtocolMap::from(self)
+    }
+
+    // The sockets of the mask for one protocol, empty when the mask does not cover it.
+    pub fn to_collapsed_plane(&self, protocol: u8) -> RangeSet<Socket> {
+        if self.range_protocols.iter().any(|x| x.begin <= protocol && protocol <= x.end) {
+            RangeSet::from_sorted_iter(self.sockets())
+        } else {
+            RangeSet::empty()
+        }
+    }
 
-    let a_bytes = (a as u32).to_be_bytes();
-    let b_bytes = (b as u16).to_be_bytes();
+    // One mask per protocol and port pattern, merging the protocols of identical masks.
+    pub fn from_collapsed(sockets: &ProtocolMap<RangeSet<Socket>>) -> Vec<Mask> {
+        ProtocolMap::from_collapsed(sockets).to_masks()
+    }
 
-    let buff = [a_bytes.as_slice(), b_bytes.as_slice()].concat();
+}
 
-    u64::from_be_bytes([
-        0,
-        0,
-        buff[0],
-        buff[1],
-        buff[2],
-        buff[3],
-        buff[4],
-        buff[5],
-    ])
 
+pub struct Sockets {
+    ips: RangeSet<IP>,
+    ports: RangeSet<Port>,
+    full_ports: bool,
+    ip_cnt: usize,
+    port_cnt: usize,
+    ip: u32,
+    // Last socket produced, held back until the next one can not extend it.
+    pending: Option<Socket>,
 }
 
 
-impl Mask {
+impl Sockets {
+
+    pub(crate) fn new(ips: RangeSet<IP>, ports: RangeSet<Port>) -> Self {
+
+        let ip = ips.ranges().first().map_or(0, |x| x.begin);
 
-    pub fn to_collapsed(&self) -> Op {
+        Sockets {
+            full_ports: ports.is_full(),
+            ips,
+            ports,
+            ip_cnt: 0,
+            port_cnt: 0,
+            ip,
+            pending: None,
+        }
+
+    }
 
-        let mut ranges: Vec<Box<dyn Range>> = vec![];
+    // One socket per IP and port range, or per IP range when every port is covered.
+    fn next_raw(&mut self) -> Option<Socket> {
 
-        for addr in &self.range_ips {
-            for ip in addr.begin..addr.end + 1 {
-                for port_range in &self.range_ports {
-                    let begin = convert(ip, port_range.begin);
-                    let end = convert(ip, port_range.end);
+        let addr = *self.ips.ranges().get(self.ip_cnt)?;
 
-                    let socket = Socket::new(begin, end);
-                    ranges.push(Box::new(socket));
+        if self.full_ports {
+            self.ip_cnt += 1;
+            return Some(Socket::new(convert(addr.begin, 0), convert(addr.end, MAX_PORT)));
+        }
+
+        let port_range = *self.ports.ranges().get(self.port_cnt)?;
+        let socket = Socket::new(convert(self.ip, port_range.begin), convert(self.ip, port_range.end));
+
+        self.port_cnt += 1;
+        if self.port_cnt == self.ports.count_ranges() {
+            self.port_cnt = 0;
+            if self.ip >= addr.end {
+                self.ip_cnt += 1;
+                if let Some(next) = self.ips.ranges().get(self.ip_cnt) {
+                    self.ip = next.begin;
                 }
+            } else {
+                self.ip += 1;
             }
         }
 
-        Op {ranges, range_of: Some(TypeRange::IP)}
+        Some(socket)
+
+    }
+
+}
+
+
+// `Sockets` always come sorted and merged, so they stream into the set operators.
+impl RangeSet<Socket> {
+
+    pub fn union_sockets(&self, sockets: Sockets) -> Self { self.union_sorted(sockets) }
+
+    pub fn intersect_sockets(&self, sockets: Sockets) -> Self { self.intersect_sorted(sockets) }
+
+}
+
+
+impl Iterator for Sockets {
+
+    type Item = Socket;
+
+    // Merges sockets that continue across an IP boundary, e.g. ports
+    // 60000-65535 of one IP followed by ports 0-100 of the next one.
+    fn next(&mut self) -> Option<Socket> {
+
+        while let Some(socket) = self.next_raw() {
+            match &mut self.pending {
+                Some(pending) if pending.end + 1 == socket.begin => pending.end = socket.end,
+                Some(_) => return self.pending.replace(socket),
+                None => self.pending = Some(socket),
+            }
+        }
+
+        self.pending.take()
 
     }
 
@@ -60,10 +203,185 @@ impl Mask {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ranges::mask_set::MaskSet;
+
+    #[test]
+    fn test_try_new() {
+
+        assert!(Mask::try_new(vec![IP::new(0, 10)], vec![Port::new(80, 443)], vec![Protocol::TCP]).is_ok());
+        assert_eq!(
+            Mask::try_new(vec![IP::new(0, 10)], vec![Port::new(443, 80)], vec![Protocol::TCP]).err(),
+            Some(RangeError::Inverted { begin: 443, end: 80 })
+        );
+        assert_eq!(
+            Mask::try_new(vec![IP::new(10, 3)], vec![Port::new(80, 443)], vec![Protocol::TCP]).err(),
+            Some(RangeError::Inverted { begin: 10, end: 3 })
+        );
+        assert_eq!(
+            Mask::try_new(vec![IP::new(0, 10)], vec![Port::new(80, 443)], vec![Protocol::new(17, 6)]).err(),
+            Some(RangeError::Inverted { begin: 17, end: 6 })
+        );
+
+    }
+
+    // Reference expansion: one socket per IP and port range.
+    fn naive_collapsed(mask: &Mask) -> RangeSet<Socket> {
+        let mut ranges: Vec<Socket> = vec![];
+        for addr in &mask.range_ips {
+            for ip in addr.begin..=addr.end {
+                for port in &mask.range_ports {
+                    ranges.push(Socket::new(convert(ip, port.begin), convert(ip, port.end)));
+                }
+            }
+        }
+        RangeSet::from_ranges(ranges).unwrap()
+    }
+
+    #[test]
+    fn test_to_collapsed() {
+
+        let masks = vec![
+            Mask::new(vec![IP::new(0, 0)], vec![Port::new(0, 1000), Port::new(1500, 2000)]),
+            Mask::new(
+                vec![IP::new(5, 7), IP::new(1, 2)],
+                vec![Port::new(80, 80), Port::new(443, 443)],
+            ),
+            Mask::new(vec![IP::new(10, 12)], vec![Port::new(60000, MAX_PORT), Port::new(0, 100)]),
+            Mask::new(vec![IP::new(10, 12), IP::new(13, 14)], vec![Port::new(0, MAX_PORT)]),
+            Mask::new(vec![IP::new(10, 12)], vec![]),
+            Mask::new(vec![], vec![Port::new(0, 10)]),
+        ];
+
+        for mask in &masks {
+            assert_eq!(mask.to_collapsed_plane(Protocol::TCP.begin), naive_collapsed(mask));
+            assert_eq!(mask.to_collapsed(), ProtocolMap::new(vec![Protocol::ANY], naive_collapsed(mask)));
+        }
+
+        let wrapped = masks[2].to_collapsed_plane(Protocol::TCP.begin);
+        assert_eq!(wrapped.count_ranges(), 4);
+        assert_eq!(wrapped.ranges()[1], Socket::new(convert(10, 60000), convert(11, 100)));
+
+    }
+
+    #[test]
+    fn test_to_collapsed_full_ports() {
+
+        let mask = Mask::new(
+            vec!["10.0.0.0/8".parse().unwrap(), "192.168.0.0/16".parse().unwrap()],
+            vec![Port::new(0, 1023), Port::new(1024, MAX_PORT)],
+        ).with_protocols(vec![Protocol::TCP]);
+
+        let collapsed = mask.to_collapsed_plane(Protocol::TCP.begin);
+        assert_eq!(collapsed.ranges(), &[
+            Socket::new(convert(0x0a000000, 0), convert(0x0affffff, MAX_PORT)),
+            Socket::new(convert(0xc0a80000, 0), convert(0xc0a8ffff, MAX_PORT)),
+        ]);
+        assert_eq!(mask.sockets().count(), 2);
+        assert!(mask.to_collapsed_plane(Protocol::UDP.begin).is_empty());
+        assert_eq!(mask.to_collapsed().entries(), &[(Protocol::TCP, collapsed.clone())]);
+
+        let other = Mask::new(vec![IP::new(0x0a000000, 0x0a0000ff)], vec![Port::new(80, 80)])
+            .with_protocols(vec![Protocol::TCP]);
+        let other = other.to_collapsed_plane(Protocol::TCP.begin);
+        assert_eq!(other.union_sockets(mask.sockets()), &collapsed | &other);
+        assert_eq!(other.intersect_sockets(mask.sockets()), other);
+        assert_eq!(RangeSet::empty().union_sockets(mask.sockets()), collapsed);
+
+    }
 
     #[test]
-    fn test_convert() {
-        assert_eq!(convert(10, 20), 655380);
+    fn test_to_collapsed_invalid_ranges() {
+
+        let inverted = Mask::new(vec![IP::new(10, 3)], vec![Port::new(80, 80)]);
+        assert_eq!(inverted.sockets().count(), 0);
+        assert!(inverted.to_collapsed().is_empty());
+
+        let mixed = Mask::new(
+            vec![IP::new(10, 3), IP::new(20, 21)],
+            vec![Port::new(443, 80), Port::new(22, 22)],
+        ).with_protocols(vec![Protocol::new(17, 6), Protocol::TCP]);
+        let valid = Mask::new(vec![IP::new(20, 21)], vec![Port::new(22, 22)])
+            .with_protocols(vec![Protocol::TCP]);
+        assert_eq!(mixed.to_collapsed(), valid.to_collapsed());
+        assert_eq!(ProtocolMap::<MaskSet>::from(&mixed), ProtocolMap::from(&valid));
+
+        let last = Mask::new(vec![IP::new(u32::MAX - 1, u32::MAX)], vec![Port::new(80, 80)]);
+        assert_eq!(last.sockets().count(), 2);
+
+    }
+
+    #[test]
+    fn test_from_collapsed() {
+
+        let mask = Mask::new(
+            vec![IP::new(5, 7), IP::new(1, 2)],
+            vec![Port::new(443, 443), Port::new(80, 80)],
+        );
+        assert_eq!(Mask::from_collapsed(&mask.to_collapsed()), vec![
+            Mask::new(
+                vec![IP::new(1, 2), IP::new(5, 7)],
+                vec![Port::new(80, 80), Port::new(443, 443)],
+            ),
+        ]);
+
+        let full = Mask::new(vec!["10.0.0.0/8".parse().unwrap()], vec![Port::new(0, MAX_PORT)]);
+        assert_eq!(Mask::from_collapsed(&full.to_collapsed()), vec![full.clone()]);
+
+        let a = Mask::new(vec![IP::new(0, 9)], vec![Port::new(0, 1000)]);
+        let b = Mask::new(vec![IP::new(3, 4)], vec![Port::new(500, 600)]);
+        let diff = &a.to_collapsed() - &b.to_collapsed();
+        assert_eq!(Mask::from_collapsed(&diff), vec![
+            Mask::new(vec![IP::new(0, 2), IP::new(5, 9)], vec![Port::new(0, 1000)]),
+            Mask::new(vec![IP::new(3, 4)], vec![Port::new(0, 499), Port::new(601, 1000)]),
+        ]);
+
+        assert!(Mask::from_collapsed(&ProtocolMap::empty()).is_empty());
+
+        let tcp = Mask::new(vec![IP::new(0, 9)], vec![Port::new(80, 80)])
+            .with_protocols(vec![Protocol::TCP]);
+        assert_eq!(Mask::from_collapsed(&tcp.to_collapsed()), vec![tcp.clone()]);
+
+
+    }
+
+    #[test]
+    fn test_from_collapsed_spanning_ips() {
+
+        let op = RangeSet::from_ranges(vec![
+            Socket::new(convert(10, 60000), convert(13, 100)),
+            Socket::new(convert(13, 200), convert(13, 300)),
+        ]).unwrap();
+
+        let masks = Mask::from_collapsed(&ProtocolMap::new(vec![Protocol::UDP], op.clone()));
+        assert_eq!(masks, vec![
+            Mask::new(vec![IP::new(10, 10)], vec![Port::new(60000, MAX_PORT)])
+                .with_protocols(vec![Protocol::UDP]),
+            Mask::new(vec![IP::new(11, 12)], vec![Port::new(0, MAX_PORT)])
+                .with_protocols(vec![Protocol::UDP]),
+            Mask::new(vec![IP::new(13, 13)], vec![Port::new(0, 100), Port::new(200, 300)])
+                .with_protocols(vec![Protocol::UDP]),
+        ]);
+
+        let back: RangeSet<Socket> = masks.iter().map(|x| x.to_collapsed_plane(Protocol::UDP.begin)).collect();
+        assert_eq!(back, op);
+
+        let full = ProtocolMap::new(vec![Protocol::ANY], RangeSet::<Socket>::full());
+        assert_eq!(Mask::from_collapsed(&full), vec![
+            Mask::new(vec![IP::new(0, u32::MAX)], vec![Port::new(0, MAX_PORT)]),
+        ]);
+
+    }
+
+    #[test]
+    fn test_display() {
+
+        let mask = Mask::new(
+            vec!["10.0.0.0/8".parse().unwrap(), IP::new(0xc0a80001, 0xc0a80001)],
+            vec![Port::new(80, 80), Port::new(8000, 8080)],
+        ).with_protocols(vec![Protocol::TCP, Protocol::new(47, 47)]);
+        assert_eq!(mask.to_string(), "[10.0.0.0/8, 192.168.0.1] x [80, 8000-8080] x [tcp, 47]");
+        assert_eq!(Mask::new(vec![], vec![]).with_protocols(vec![]).to_string(), "[] x [] x []");
+
     }
 
 }
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitXor, Not, Sub};
+
+use crate::ranges::logic::RangeSet;
+use crate::ranges::mask::{Mask, Sockets};
+use crate::ranges::types::{Bound, Port, Protocol, Range, IP, Socket, SocketAddrRange, MAX_PORT};
+
+
+// Disjoint IP x port rectangles, stored as ascending IP rows with their port set.
+// Adjacent rows always differ in ports, so equal sets have equal rows.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MaskSet {
+    rows: Vec<(IP, RangeSet<Port>)>,
+}
+
+
+// Appends a row, extending the previous one when it is adjacent with the same value.
+pub(crate) fn push_row<K: Range, V: PartialEq>(rows: &mut Vec<(K, V)>, key: K, value: V) {
+
+    if let Some((last, last_value)) = rows.last_mut()
+        && last.end() < K::MAX
+        && last.end().succ() == key.begin()
+        && *last_value == value
+    {
+        last.set_end(key.end());
+        return;
+    }
+
+    rows.push((key, value));
+
+}
+
+
+// Applies `op` to the values of `a` and `b` on every segment between their row boundaries.
+// `op` returns None for an empty result, which drops the segment.
+pub(crate) fn combine_rows<K, V, F>(a: &[(K, V)], b: &[(K, V)], empty: &V, op: F) -> Vec<(K, V)>
+where
+    K: Range,
+    V: PartialEq,
+    F: Fn(&V, &V) -> Option<V>,
+{
+
+    let mut cuts: Vec<K::Bound> = a.iter().chain(b)
+        .flat_map(|(key, _)| [Some(key.begin()), (key.end() < K::MAX).then(|| key.end().succ())])
+        .flatten()
+        .collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let (mut i, mut j) = (0, 0);
+    let mut rows = vec![];
+
+    for (n, &begin) in cuts.iter().enumerate() {
+
+        let end = cuts.get(n + 1).map_or(K::MAX, |x| x.pred());
+
+        while i < a.len() && a[i].0.end() < begin { i += 1; }
+        while j < b.len() && b[j].0.end() < begin { j += 1; }
+
+        let value_a = a.get(i).filter(|(key, _)| key.begin() <= begin).map_or(empty, |(_, x)| x);
+        let value_b = b.get(j).filter(|(key, _)| key.begin() <= begin).map_or(empty, |(_, x)| x);
+
+        if let Some(value) = op(value_a, value_b) {
+            push_row(&mut rows, K::from_bounds(begin, end), value);
+        }
+
+    }
+
+    rows
+
+}
+
+
+impl MaskSet {
+
+    pub fn empty() -> Self { MaskSet { rows: vec![] } }
+
+    pub fn full() -> Self { MaskSet { rows: vec![(IP::new(0, u32::MAX), RangeSet::full())] } }
+
+    pub fn is_empty(&self) -> bool { self.rows.is_empty() }
+
+    pub fn rows(&self) -> &[(IP, RangeSet<Port>)] { &self.rows }
+
+    pub fn contains(&self, ip: u32, port: u16) -> bool {
+        let i = self.rows.partition_point(|(x, _)| x.end < ip);
+        self.rows.get(i).is_some_and(|(x, ports)| x.begin <= ip && ports.contains(port))
+    }
+
+    // Groups the IP rows by port pattern, one entry per distinct pattern.
+    fn groups(&self) -> Vec<(Vec<IP>, &RangeSet<Port>)> {
+
+        let mut index: HashMap<&RangeSet<Port>, usize> = HashMap::new();
+        let mut groups: Vec<(Vec<IP>, &RangeSet<Port>)> = vec![];
+
+        for (ip, ports) in &self.rows {
+            match index.get(ports) {
+                Some(&i) => groups[i].0.push(*ip),
+                None => {
+                    index.insert(ports, groups.len());
+                    groups.push((vec![*ip], ports));
+                }
+            }
+        }
+
+        groups
+
+    }
+
+    // One mask per port pattern, for the protocols the caller attaches to this plane.
+    pub fn to_masks(&self, protocols: Vec<Protocol>) -> Vec<Mask> {
+        self.groups().into_iter()
+            .map(|(ips, ports)| Mask::new(ips, ports.ranges().to_vec()).with_protocols(protocols.clone()))
+            .collect()
+    }
+
+    // Rows are ascending, so their sockets come sorted.
+    pub fn to_collapsed(&self) -> RangeSet<Socket> {
+        RangeSet::from_sorted_iter(self.rows.iter()
+            .flat_map(|(ip, ports)| Sockets::new(RangeSet::normalized(vec![*ip]), ports.clone())))
+    }
+
+    pub fn from_collapsed(sockets: &RangeSet<Socket>) -> Self {
+
+        let mut rows = vec![];
+        let mut ip = 0;
+        let mut ports: Vec<Port> = vec![];
+
+        for socket in sockets.ranges() {
+
+            let range = SocketAddrRange::from(*socket);
+            let IP { begin: ip_begin, end: ip_end } = range.ip();
+            let (port_begin, port_end) = (range.begin.port(), range.end.port());
+
+            if ip_begin != ip && !ports.is_empty() {
+                push_row(&mut rows, IP::new(ip, ip), RangeSet::normalized(std::mem::take(&mut ports)));
+            }
+            ip = ip_begin;
+
+            if ip_begin == ip_end {
+                ports.push(Port::new(port_begin, port_end));
+                continue;
+            }
+
+            // The socket crosses IP boundaries: tail of the first IP, whole IPs, head of the last.
+            ports.push(Port::new(port_begin, MAX_PORT));
+            push_row(&mut rows, IP::new(ip, ip), RangeSet::normalized(std::mem::take(&mut ports)));
+            if ip_end - ip_begin > 1 {
+                push_row(&mut rows, IP::new(ip_begin + 1, ip_end - 1), RangeSet::full());
+            }
+            ip = ip_end;
+            ports.push(Port::new(0, port_end));
+
+        }
+
+        if !ports.is_empty() {
+            push_row(&mut rows, IP::new(ip, ip), RangeSet::normalized(ports));
+        }
+
+        MaskSet { rows }
+
+    }
+
+}
+
+
+// `{[0.0.0.0-0.0.0.4] x [80]}`, planes carry no protocol.
+impl fmt::Display for MaskSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |items: Vec<String>| items.join(", ");
+        let groups: Vec<String> = self.groups().iter()
+            .map(|(ips, ports)| {
+                let ips = join(ips.iter().map(|x| x.to_string()).collect());
+                let ports = join(ports.ranges().iter().map(|x| x.to_string()).collect());
+                format!("[{ips}] x [{ports}]")
+            })
+            .collect();
+        write!(f, "{{{}}}", groups.join(", "))
+    }
+}
+
+
+// The IP x port plane of a mask, whatever its protocols.
+impl From<&Mask> for MaskSet {
+
+    fn from(mask: &Mask) -> Self {
+
+        let ports = RangeSet::from_valid_ranges(mask.range_ports.clone());
+        if ports.is_empty() {
+            return MaskSet::empty();
+        }
+
+        let ips = RangeSet::from_valid_ranges(mask.range_ips.clone());
+        MaskSet { rows: ips.ranges().iter().map(|ip| (*ip, ports.clone())).collect() }
+
+    }
+
+}
+
+
+impl From<Mask> for MaskSet {
+    fn from(mask: Mask) -> Self { MaskSet::from(&mask) }
+}
+
+
+impl Not for &MaskSet {
+
+    type Output = MaskSet;
+
+    fn not(self) -> Self::Output { &MaskSet::full() - self }
+
+}
+
+
+// Row-wise operators on mask sets.
+macro_rules! mask_binop {
+    ($imp:ident, $method:ident) => {
+
+        impl $imp<&MaskSet> for &MaskSet {
+
+            type Output = MaskSet;
+
+            fn $method(self, rhs: &MaskSet) -> Self::Output {
+                let empty = RangeSet::empty();
+                let rows = combine_rows(&self.rows, &rhs.rows, &empty, |a, b| Some(a.$method(b)).filter(|x| !x.is_empty()));
+                MaskSet { rows }
+            }
+
+        }
+
+    };
+}
+
+mask_binop!(BitOr, bitor);
+mask_binop!(BitAnd, bitand);
+mask_binop!(Sub, sub);
+mask_binop!(BitXor, bitxor);
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ranges::types::convert;
+
+    fn plane(mask: &Mask) -> RangeSet<Socket> { mask.to_collapsed_plane(Protocol::TCP.begin) }
+
+    fn masks() -> Vec<Mask> {
+        vec![
+            Mask::new(vec![IP::new(0, 9)], vec![Port::new(0, 1000)]),
+            Mask::new(
+                vec![IP::new(3, 4), IP::new(8, 12)],
+                vec![Port::new(500, 600), Port::new(2000, 3000)],
+            ),
+            Mask::new(vec![IP::new(5, 20)], vec![Port::new(60000, MAX_PORT), Port::new(0, 10)]),
+            Mask::new(vec![IP::new(2, 2)], vec![Port::new(0, MAX_PORT)]),
+            Mask::new(vec![IP::new(2, 6)], vec![]),
+        ]
+    }
+
+    #[test]
+    fn test_mask_ops_match_collapsed() {
+
+        for a in &masks() {
+            for b in &masks() {
+
+                let (sa, sb) = (plane(a), plane(b));
+                let (a, b) = (&MaskSet::from(a), &MaskSet::from(b));
+
+                assert_eq!((a | b).to_collapsed(), &sa | &sb);
+                assert_eq!((a & b).to_collapsed(), &sa & &sb);
+                assert_eq!((a - b).to_collapsed(), &sa - &sb);
+                assert_eq!((a ^ b).to_collapsed(), &sa ^ &sb);
+
+            }
+        }
+
+    }
+
+    #[test]
+    fn test_mask_not() {
+
+        for mask in &masks() {
+            let not = !&MaskSet::from(mask);
+            assert_eq!(not.to_collapsed(), !&plane(mask));
+            assert_eq!(!&not, MaskSet::from(mask));
+        }
+
+        assert_eq!(!&MaskSet::empty(), MaskSet::full());
+        assert!((!&MaskSet::full()).is_empty());
+
+    }
+
+    #[test]
+    fn test_mask_set_canonical() {
+
+        let a = MaskSet::from(Mask::new(vec![IP::new(0, 4)], vec![Port::new(80, 80)]));
+        let b = MaskSet::from(Mask::new(vec![IP::new(5, 9)], vec![Port::new(80, 80)]));
+        let c = MaskSet::from(Mask::new(vec![IP::new(0, 9)], vec![Port::new(80, 80)]));
+
+        assert_eq!(&a | &b, c);
+        assert_eq!((&a | &b).rows().len(), 1);
+        assert_eq!(MaskSet::from_collapsed(&(&a | &b).to_collapsed()), c);
+        assert_eq!((&c - &b).to_string(), "{[0.0.0.0-0.0.0.4] x [80]}");
+        assert_eq!(MaskSet::empty().to_string(), "{}");
+
+    }
+
+    #[test]
+    fn test_mask_ops_scale_with_boundaries() {
+
+        let net: IP = "10.0.0.0/8".parse().unwrap();
+        let hole: IP = "10.1.0.0/16".parse().unwrap();
+
+        let a = Mask::new(vec![net], vec![Port::new(0, 1023)]);
+        let b = Mask::new(vec![hole], vec![Port::new(22, 22)]);
+
+        let diff = &MaskSet::from(&a) - &MaskSet::from(&b);
+        assert_eq!(diff.rows().len(), 3);
+        assert_eq!(diff.to_masks(vec![Protocol::ANY]), vec![
+            Mask::new(
+                vec![IP::new(net.begin, hole.begin - 1), IP::new(hole.end + 1, net.end)],
+                vec![Port::new(0, 1023)],
+            ),
+            Mask::new(vec![hole], vec![Port::new(0, 21), Port::new(23, 1023)]),
+        ]);
+
+        let inter = &MaskSet::from(&a) & &MaskSet::from(&b);
+        assert_eq!(inter.to_masks(vec![Protocol::ANY]), vec![b.clone()]);
+        assert_eq!(inter.to_masks(vec![Protocol::TCP])[0].range_protocols, vec![Protocol::TCP]);
+        assert!(inter.to_collapsed().contains(convert(hole.begin, 22)));
+
+    }
+
+}
@@ -1,4 +1,10 @@
-
+pub mod cidr;
+pub mod error;
 pub mod logic;
 pub mod types;
 pub mod mask;
+pub mod mask_set;
+pub mod parse;
+pub mod port_mask;
+pub mod protocol_map;
+pub mod rules;
@@ -0,0 +1,225 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use crate::ranges::error::RangeError;
+use crate::ranges::logic::RangeSet;
+use crate::ranges::types::{IP, IPv6};
+
+
+fn parse_addr(s: &str) -> Result<u32, RangeError<u32>> {
+    Ipv4Addr::from_str(s.trim())
+        .map(u32::from)
+        .map_err(|_| RangeError::InvalidAddress(s.trim().to_string()))
+}
+
+
+impl From<Ipv4Addr> for IP {
+
+    fn from(addr: Ipv4Addr) -> Self {
+        let value = u32::from(addr);
+        IP::new(value, value)
+    }
+
+}
+
+
+// Accepts `a.b.c.d`, `a.b.c.d/nn` and `a.b.c.d-e.f.g.h`. Host bits under
+// a prefix are cleared, the same way iptables treats `10.0.0.1/8`.
+impl FromStr for IP {
+
+    type Err = RangeError<u32>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+        if let Some((addr, prefix)) = s.split_once('/') {
+
+            let prefix: u32 = prefix.trim().parse()
+                .ok()
+                .filter(|x| *x <= 32)
+                .ok_or_else(|| RangeError::InvalidPrefix(prefix.trim().to_string()))?;
+
+            let host_bits: u32 = u32::MAX.checked_shr(prefix).unwrap_or(0);
+            let begin = parse_addr(addr)? & !host_bits;
+
+            return Ok(IP::new(begin, begin | host_bits));
+
+        }
+
+        if let Some((begin, end)) = s.split_once('-') {
+            return IP::try_new(parse_addr(begin)?, parse_addr(end)?);
+        }
+
+        let value = parse_addr(s)?;
+        Ok(IP::new(value, value))
+
+    }
+
+}
+
+
+fn parse_addr6(s: &str) -> Result<u128, RangeError<u128>> {
+    Ipv6Addr::from_str(s.trim())
+        .map(u128::from)
+        .map_err(|_| RangeError::InvalidAddress(s.trim().to_string()))
+}
+
+
+impl From<Ipv6Addr> for IPv6 {
+
+    fn from(addr: Ipv6Addr) -> Self {
+        let value = u128::from(addr);
+        IPv6::new(value, value)
+    }
+
+}
+
+
+// Same forms as for IP: `2001:db8::1`, `2001:db8::/32` and `2001:db8::1-2001:db8::ff`.
+impl FromStr for IPv6 {
+
+    type Err = RangeError<u128>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+        if let Some((addr, prefix)) = s.split_once('/') {
+
+            let prefix: u32 = prefix.trim().parse()
+                .ok()
+                .filter(|x| *x <= 128)
+                .ok_or_else(|| RangeError::InvalidPrefix(prefix.trim().to_string()))?;
+
+            let host_bits: u128 = u128::MAX.checked_shr(prefix).unwrap_or(0);
+            let begin = parse_addr6(addr)? & !host_bits;
+
+            return Ok(IPv6::new(begin, begin | host_bits));
+
+        }
+
+        if let Some((begin, end)) = s.split_once('-') {
+            return IPv6::try_new(parse_addr6(begin)?, parse_addr6(end)?);
+        }
+
+        let value = parse_addr6(s)?;
+        Ok(IPv6::new(value, value))
+
+    }
+
+}
+
+
+pub fn parse_ip_list<I, S>(items: I) -> Result<RangeSet<IP>, RangeError<u32>>
+    where I: IntoIterator<Item = S>, S: AsRef<str>
+{
+
+    let ranges = items.into_iter()
+        .map(|x| x.as_ref().parse::<IP>())
+        .collect::<Result<Vec<IP>, RangeError<u32>>>()?;
+
+    RangeSet::from_ranges(ranges)
+
+}
+
+
+pub fn parse_ipv6_list<I, S>(items: I) -> Result<RangeSet<IPv6>, RangeError<u128>>
+    where I: IntoIterator<Item = S>, S: AsRef<str>
+{
+
+    let ranges = items.into_iter()
+        .map(|x| x.as_ref().parse::<IPv6>())
+        .collect::<Result<Vec<IPv6>, RangeError<u128>>>()?;
+
+    RangeSet::from_ranges(ranges)
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ranges::types::MAX_IP;
+
+    #[test]
+    fn test_parse_address() {
+
+        assert_eq!("10.0.0.1".parse::<IP>(), Ok(IP::new(167772161, 167772161)));
+        assert_eq!(" 0.0.0.0 ".parse::<IP>(), Ok(IP::new(0, 0)));
+        assert_eq!(IP::from(Ipv4Addr::new(255, 255, 255, 255)), IP::new(MAX_IP, MAX_IP));
+
+    }
+
+    #[test]
+    fn test_parse_cidr() {
+
+        assert_eq!("10.0.0.0/8".parse::<IP>(), Ok(IP::new(167772160, 184549375)));
+        assert_eq!("10.1.2.3/8".parse::<IP>(), Ok(IP::new(167772160, 184549375)));
+        assert_eq!("0.0.0.0/0".parse::<IP>(), Ok(IP::new(0, MAX_IP)));
+        assert_eq!("192.168.1.1/32".parse::<IP>(), Ok(IP::new(3232235777, 3232235777)));
+
+    }
+
+    #[test]
+    fn test_parse_range() {
+
+        assert_eq!("10.0.0.0-10.0.0.255".parse::<IP>(), Ok(IP::new(167772160, 167772415)));
+        assert_eq!(
+            "10.0.0.255-10.0.0.0".parse::<IP>(),
+            Err(RangeError::Inverted { begin: 167772415, end: 167772160 })
+        );
+
+    }
+
+    #[test]
+    fn test_parse_errors() {
+
+        assert_eq!("10.0.0.256".parse::<IP>(), Err(RangeError::InvalidAddress("10.0.0.256".to_string())));
+        assert_eq!("10.0.0".parse::<IP>(), Err(RangeError::InvalidAddress("10.0.0".to_string())));
+        assert_eq!("10.0.0.0/33".parse::<IP>(), Err(RangeError::InvalidPrefix("33".to_string())));
+        assert_eq!("10.0.0.0/x".parse::<IP>(), Err(RangeError::InvalidPrefix("x".to_string())));
+        assert_eq!("a-10.0.0.1".parse::<IP>(), Err(RangeError::InvalidAddress("a".to_string())));
+
+    }
+
+    #[test]
+    fn test_parse_ip_list() {
+
+        let op = parse_ip_list(["10.0.0.0/24", "10.0.1.0-10.0.1.255", "10.0.0.7", "192.168.0.1"]).unwrap();
+        assert_eq!(op.ranges(), &[IP::new(167772160, 167772671), IP::new(3232235521, 3232235521)]);
+
+        assert!(parse_ip_list(["10.0.0.0/24", "300.0.0.0"]).is_err());
+
+    }
+
+    #[test]
+    fn test_parse_ipv6() {
+
+        let base: u128 = 0x2001_0db8 << 96;
+
+        assert_eq!("2001:db8::1".parse::<IPv6>(), Ok(IPv6::new(base + 1, base + 1)));
+        assert_eq!("2001:db8::/32".parse::<IPv6>(), Ok(IPv6::new(base, base | (u128::MAX >> 32))));
+        assert_eq!("2001:db8::1/128".parse::<IPv6>(), Ok(IPv6::new(base + 1, base + 1)));
+        assert_eq!("::/0".parse::<IPv6>(), Ok(IPv6::new(0, u128::MAX)));
+        assert_eq!("2001:db8::1-2001:db8::ff".parse::<IPv6>(), Ok(IPv6::new(base + 1, base + 0xff)));
+        assert_eq!(IPv6::from(Ipv6Addr::LOCALHOST), IPv6::new(1, 1));
+
+    }
+
+    #[test]
+    fn test_parse_ipv6_errors() {
+
+        assert_eq!("2001:db8::g".parse::<IPv6>(), Err(RangeError::InvalidAddress("2001:db8::g".to_string())));
+        assert_eq!("10.0.0.1".parse::<IPv6>(), Err(RangeError::InvalidAddress("10.0.0.1".to_string())));
+        assert_eq!("2001:db8::/129".parse::<IPv6>(), Err(RangeError::InvalidPrefix("129".to_string())));
+        assert_eq!("::2-::1".parse::<IPv6>(), Err(RangeError::Inverted { begin: 2, end: 1 }));
+
+    }
+
+    #[test]
+    fn test_parse_ipv6_list() {
+
+        let op = parse_ipv6_list(["2001:db8::/33", "2001:db8:8000::/33", "::1"]).unwrap();
+        assert_eq!(op.ranges(), &[IPv6::new(1, 1), "2001:db8::/32".parse().unwrap()]);
+
+    }
+
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+
+use crate::ranges::cidr::prefix_blocks;
+use crate::ranges::logic::RangeSet;
+use crate::ranges::types::{Port, MAX_PORT};
+
+
+// A TCAM / OpenFlow style match: port `p` matches when `p & mask == value`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PortMask {
+    pub value: u16,
+    pub mask: u16,
+}
+
+
+impl PortMask {
+
+    pub fn matches(&self, port: u16) -> bool { port & self.mask == self.value }
+
+    pub fn to_port(&self) -> Port {
+        Port::new(self.value, self.value | !self.mask)
+    }
+
+}
+
+
+impl fmt::Display for PortMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06x}/{:#06x}", self.value, self.mask)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortMasks {
+    pub masks: Vec<PortMask>,
+    pub ranges: usize,
+}
+
+
+impl PortMasks {
+
+    // Entries needed per source interval, 1.0 when every interval is a prefix.
+    pub fn expansion_factor(&self) -> f64 {
+        if self.ranges == 0 {
+            return 0.0;
+        }
+        self.masks.len() as f64 / self.ranges as f64
+    }
+
+}
+
+
+pub fn port_range_masks(port: &Port) -> Vec<PortMask> {
+    prefix_blocks(port.begin as u64, port.end as u64, 16)
+        .into_iter()
+        .map(|(begin, prefix)| PortMask {
+            value: begin as u16,
+            mask: MAX_PORT.checked_shl(16 - prefix).unwrap_or(0),
+        })
+        .collect()
+}
+
+
+impl RangeSet<Port> {
+
+    pub fn to_port_masks(&self) -> PortMasks {
+        PortMasks {
+            masks: self.ranges().iter().flat_map(port_range_masks).collect(),
+            ranges: self.count_ranges(),
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_port_range_masks() {
+
+        assert_eq!(port_range_masks(&Port::new(80, 80)), vec![PortMask { value: 80, mask: 0xffff }]);
+        assert_eq!(port_range_masks(&Port::new(0, MAX_PORT)), vec![PortMask { value: 0, mask: 0 }]);
+        assert_eq!(
+            port_range_masks(&Port::new(1024, 2047)),
+            vec![PortMask { value: 1024, mask: 0xfc00 }]
+        );
+        assert_eq!(
+            port_range_masks(&Port::new(80, 90)),
+            vec![
+                PortMask { value: 80, mask: 0xfff8 },
+                PortMask { value: 88, mask: 0xfffe },
+                PortMask { value: 90, mask: 0xffff },
+            ]
+        );
+        assert_eq!(PortMask { value: 1024, mask: 0xfc00 }.to_string(), "0x0400/0xfc00");
+
+    }
+
+    #[test]
+    fn test_port_masks_match_exactly() {
+
+        let op = RangeSet::from_ranges(vec![Port::new(1, 1000), Port::new(8080, 8090), Port::new(60000, MAX_PORT)]).unwrap();
+        let masks = op.to_port_masks();
+
+        for port in 0..=MAX_PORT {
+            let matched = masks.masks.iter().filter(|x| x.matches(port)).count();
+            assert_eq!(matched, op.contains(port) as usize, "port {port}");
+        }
+
+        let back = RangeSet::from_ranges(masks.masks.iter().map(PortMask::to_port).collect()).unwrap();
+        assert_eq!(back, op);
+
+    }
+
+    #[test]
+    fn test_expansion_factor() {
+
+        let op = RangeSet::from_ranges(vec![Port::new(1, MAX_PORT - 1)]).unwrap();
+        let masks = op.to_port_masks();
+        assert_eq!(masks.masks.len(), 30);
+        assert_eq!(masks.expansion_factor(), 30.0);
+
+        let op = RangeSet::from_ranges(vec![Port::new(0, 1023), Port::new(8080, 8080)]).unwrap();
+        assert_eq!(op.to_port_masks().expansion_factor(), 1.0);
+
+        assert_eq!(RangeSet::<Port>::empty().to_port_masks().expansion_factor(), 0.0);
+
+    }
+
+}
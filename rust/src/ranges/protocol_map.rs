@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitXor, Not, Sub};
+
+use crate::ranges::logic::RangeSet;
+use crate::ranges::mask::Mask;
+use crate::ranges::mask_set::{combine_rows, push_row, MaskSet};
+use crate::ranges::types::{convert, Port, Protocol, IP, Socket};
+
+
+// One socket or mask set per protocol range, so that traffic of different
+// protocols never merges. Entries are ascending and disjoint, adjacent entries
+// always differ and empty sets are dropped, so equal maps have equal entries.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ProtocolMap<S> {
+    entries: Vec<(Protocol, S)>,
+}
+
+
+impl<S: Clone + Default + PartialEq> ProtocolMap<S> {
+
+    // The same set for every given protocol.
+    pub fn new(protocols: Vec<Protocol>, set: S) -> Self {
+
+        if set == S::default() {
+            return ProtocolMap::empty();
+        }
+
+        let mut entries = vec![];
+        for protocol in RangeSet::from_valid_ranges(protocols).ranges() {
+            push_row(&mut entries, *protocol, set.clone());
+        }
+
+        ProtocolMap { entries }
+
+    }
+
+    pub fn empty() -> Self { ProtocolMap { entries: vec![] } }
+
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    pub fn entries(&self) -> &[(Protocol, S)] { &self.entries }
+
+    pub fn get(&self, protocol: u8) -> Option<&S> {
+        let i = self.entries.partition_point(|(x, _)| x.end < protocol);
+        self.entries.get(i).filter(|(x, _)| x.begin <= protocol).map(|(_, set)| set)
+    }
+
+    fn combine<F: Fn(&S, &S) -> S>(&self, other: &Self, op: F) -> Self {
+        let empty = S::default();
+        let entries = combine_rows(&self.entries, &other.entries, &empty, |a, b| Some(op(a, b)).filter(|x| *x != empty));
+        ProtocolMap { entries }
+    }
+
+}
+
+
+impl ProtocolMap<RangeSet<Socket>> {
+
+    pub fn contains(&self, protocol: u8, ip: u32, port: u16) -> bool {
+        self.get(protocol).is_some_and(|set| set.contains(convert(ip, port)))
+    }
+
+}
+
+
+impl ProtocolMap<MaskSet> {
+
+    pub fn contains(&self, protocol: u8, ip: u32, port: u16) -> bool {
+        self.get(protocol).is_some_and(|set| set.contains(ip, port))
+    }
+
+    // Masks of every protocol entry, merging the protocols of identical masks.
+    pub fn to_masks(&self) -> Vec<Mask> {
+
+        let mut index: HashMap<(Vec<IP>, Vec<Port>), usize> = HashMap::new();
+        let mut masks: Vec<Mask> = vec![];
+
+        for (protocol, set) in &self.entries {
+            for mask in set.to_masks(vec![*protocol]) {
+                let key = (mask.range_ips, mask.range_ports);
+                match index.get(&key) {
+                    Some(&i) => masks[i].range_protocols.extend(mask.range_protocols),
+                    None => {
+                        index.insert(key.clone(), masks.len());
+                        masks.push(Mask::new(key.0, key.1).with_protocols(mask.range_protocols));
+                    }
+                }
+            }
+        }
+
+        masks
+
+    }
+
+    pub fn to_collapsed(&self) -> ProtocolMap<RangeSet<Socket>> {
+        ProtocolMap { entries: self.entries.iter().map(|(protocol, set)| (*protocol, set.to_collapsed())).collect() }
+    }
+
+    pub fn from_collapsed(sockets: &ProtocolMap<RangeSet<Socket>>) -> Self {
+        ProtocolMap { entries: sockets.entries.iter().map(|(protocol, set)| (*protocol, MaskSet::from_collapsed(set))).collect() }
+    }
+
+}
+
+
+impl From<&Mask> for ProtocolMap<MaskSet> {
+    fn from(mask: &Mask) -> Self { ProtocolMap::new(mask.range_protocols.clone(), MaskSet::from(mask)) }
+}
+
+
+impl From<&Mask> for ProtocolMap<RangeSet<Socket>> {
+    fn from(mask: &Mask) -> Self { ProtocolMap::new(mask.range_protocols.clone(), RangeSet::from_sorted_iter(mask.sockets())) }
+}
+
+
+impl fmt::Display for ProtocolMap<MaskSet> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let masks: Vec<String> = self.to_masks().iter().map(|x| x.to_string()).collect();
+        write!(f, "{{{}}}", masks.join(", "))
+    }
+}
+
+
+impl fmt::Display for ProtocolMap<RangeSet<Socket>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self.entries.iter().map(|(protocol, set)| format!("{protocol}: {set}")).collect();
+        write!(f, "{{{}}}", entries.join(", "))
+    }
+}
+
+
+impl<S> Not for &ProtocolMap<S>
+where
+    S: Clone + Default + PartialEq,
+    for<'a> &'a S: Not<Output = S> + Sub<&'a S, Output = S>,
+{
+
+    type Output = ProtocolMap<S>;
+
+    fn not(self) -> Self::Output {
+        let full = ProtocolMap::new(vec![Protocol::ANY], !&S::default());
+        full.combine(self, |a, b| a - b)
+    }
+
+}
+
+
+impl Not for &Mask {
+
+    type Output = ProtocolMap<MaskSet>;
+
+    fn not(self) -> Self::Output { !&ProtocolMap::<MaskSet>::from(self) }
+
+}
+
+
+// Operators applied protocol by protocol, and the same operators on single masks.
+macro_rules! protocol_binop {
+    ($imp:ident, $method:ident) => {
+
+        impl<S> $imp<&ProtocolMap<S>> for &ProtocolMap<S>
+        where
+            S: Clone + Default + PartialEq,
+            for<'a> &'a S: $imp<&'a S, Output = S>,
+        {
+
+            type Output = ProtocolMap<S>;
+
+            fn $method(self, rhs: &ProtocolMap<S>) -> Self::Output { self.combine(rhs, |a, b| a.$method(b)) }
+
+        }
+
+        impl $imp<&Mask> for &Mask {
+
+            type Output = ProtocolMap<MaskSet>;
+
+            fn $method(self, rhs: &Mask) -> Self::Output {
+                (&ProtocolMap::<MaskSet>::from(self)).$method(&ProtocolMap::from(rhs))
+            }
+
+        }
+
+    };
+}
+
+protocol_binop!(BitOr, bitor);
+protocol_binop!(BitAnd, bitand);
+protocol_binop!(Sub, sub);
+protocol_binop!(BitXor, bitxor);
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn masks() -> Vec<Mask> {
+        vec![
+            Mask::new(vec![IP::new(0, 9)], vec![Port::new(0, 1000)])
+                .with_protocols(vec![Protocol::TCP]),
+            Mask::new(vec![IP::new(3, 12)], vec![Port::new(500, 600)])
+                .with_protocols(vec![Protocol::TCP, Protocol::UDP]),
+            Mask::new(vec![IP::new(5, 20)], vec![Port::new(0, 10)]),
+            Mask::new(vec![IP::new(2, 2)], vec![Port::new(53, 53)])
+                .with_protocols(vec![Protocol::UDP]),
+            Mask::new(vec![IP::new(2, 6)], vec![Port::new(53, 53)]).with_protocols(vec![]),
+        ]
+    }
+
+    #[test]
+    fn test_protocols_kept_apart() {
+
+        let tcp = Mask::new(vec![IP::new(0, 9)], vec![Port::new(53, 53)])
+            .with_protocols(vec![Protocol::TCP]);
+        let udp = Mask::new(vec![IP::new(0, 9)], vec![Port::new(53, 53)])
+            .with_protocols(vec![Protocol::UDP]);
+
+        assert!((&tcp & &udp).is_empty());
+        assert_eq!(&tcp - &udp, ProtocolMap::from(&tcp));
+
+        let both = &tcp | &udp;
+        assert_eq!(both.entries().len(), 2);
+        assert!(both.contains(6, 5, 53));
+        assert!(both.contains(17, 5, 53));
+        assert!(!both.contains(1, 5, 53));
+        assert_eq!(both.to_masks(), vec![
+            Mask::new(vec![IP::new(0, 9)], vec![Port::new(53, 53)])
+                .with_protocols(vec![Protocol::TCP, Protocol::UDP]),
+        ]);
+        assert_eq!(both.to_string(), "{[0.0.0.0-0.0.0.9] x [53] x [tcp, udp]}");
+
+        let collapsed = both.to_collapsed();
+        assert_eq!(collapsed.get(6), collapsed.get(17));
+        assert_eq!(collapsed.get(6), Some(&tcp.to_collapsed_plane(6)));
+        assert_eq!(&tcp.to_collapsed() | &udp.to_collapsed(), collapsed);
+        assert_eq!(collapsed.get(7), None);
+        assert_eq!(ProtocolMap::<MaskSet>::from_collapsed(&collapsed), both);
+
+    }
+
+    #[test]
+    fn test_ops_match_collapsed() {
+
+        for a in &masks() {
+            for b in &masks() {
+
+                let sa = ProtocolMap::<RangeSet<Socket>>::from(a);
+                let sb = ProtocolMap::<RangeSet<Socket>>::from(b);
+
+                assert_eq!((a | b).to_collapsed(), &sa | &sb);
+                assert_eq!((a & b).to_collapsed(), &sa & &sb);
+                assert_eq!((a - b).to_collapsed(), &sa - &sb);
+                assert_eq!((a ^ b).to_collapsed(), &sa ^ &sb);
+
+            }
+        }
+
+    }
+
+    #[test]
+    fn test_not() {
+
+        let web = Mask::new(vec![IP::new(0, 9)], vec![Port::new(80, 80)])
+            .with_protocols(vec![Protocol::TCP]);
+        let not = !&web;
+
+        assert_eq!(not.entries().len(), 3);
+        assert!(not.contains(6, 0, 81));
+        assert!(not.contains(17, 0, 80));
+        assert!(!not.contains(6, 0, 80));
+        assert_eq!(!&not, ProtocolMap::from(&web));
+
+        assert!((!&ProtocolMap::<MaskSet>::new(vec![Protocol::ANY], MaskSet::full())).is_empty());
+        assert_eq!(ProtocolMap::new(vec![Protocol::TCP], MaskSet::empty()), ProtocolMap::empty());
+
+    }
+
+}
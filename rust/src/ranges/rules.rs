@@ -0,0 +1,519 @@
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use crate::ranges::error::RangeError;
+use crate::ranges::logic::RangeSet;
+use crate::ranges::mask::Mask;
+use crate::ranges::mask_set::MaskSet;
+use crate::ranges::protocol_map::ProtocolMap;
+use crate::ranges::types::{Protocol, Socket};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action { Allow, Deny }
+
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Allow => write!(f, "allow"),
+            Action::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Finding {
+    // Matches no socket at all, so it never decides anything.
+    Empty { rule: usize },
+    // Never matches first, and earlier rules with another action decide all of its sockets.
+    Shadowed { rule: usize, by: Vec<usize> },
+    // Never matches first, and earlier rules with the same action decide all of its sockets.
+    Redundant { rule: usize, by: Vec<usize> },
+    // Never matches first, and earlier rules of both actions decide its sockets.
+    Mixed { rule: usize, same: Vec<usize>, other: Vec<usize> },
+    // Matches first on some sockets but shares `region` with the earlier rule `with`.
+    Overlap { rule: usize, with: usize, region: ProtocolMap<MaskSet> },
+}
+
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |by: &[usize]| by.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", ");
+        match self {
+            Finding::Empty { rule } => write!(f, "rule {rule} matches nothing"),
+            Finding::Shadowed { rule, by } => write!(f, "rule {rule} is shadowed by rules [{}]", join(by)),
+            Finding::Redundant { rule, by } => write!(f, "rule {rule} is redundant with rules [{}]", join(by)),
+            Finding::Mixed { rule, same, other } => {
+                write!(f, "rule {rule} is covered by rules [{}] with the same action and [{}] with another", join(same), join(other))
+            }
+            Finding::Overlap { rule, with, region } => write!(f, "rule {rule} overlaps rule {with} on {region}"),
+        }
+    }
+}
+
+
+// Traffic allowed only by the new policy, and traffic it no longer allows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicyDiff {
+    pub added: Vec<Mask>,
+    pub removed: Vec<Mask>,
+}
+
+
+impl PolicyDiff {
+
+    pub fn is_empty(&self) -> bool { self.added.is_empty() && self.removed.is_empty() }
+
+}
+
+
+// One line per mask, `+` for added and `-` for removed traffic.
+impl fmt::Display for PolicyDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for mask in &self.added {
+            writeln!(f, "+ {mask}")?;
+        }
+        for mask in &self.removed {
+            writeln!(f, "- {mask}")?;
+        }
+        Ok(())
+    }
+}
+
+
+// Why a socket is allowed or denied: the deciding rule, if any, and later rules that also match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    pub protocol: u8,
+    pub addr: SocketAddrV4,
+    pub rule: Option<usize>,
+    pub action: Action,
+    pub also: Vec<usize>,
+}
+
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let protocol = Protocol::new(self.protocol, self.protocol);
+        match self.rule {
+            Some(rule) => write!(f, "{protocol} {} {} by rule {rule}", self.addr, self.action)?,
+            None => write!(f, "{protocol} {} {} by default", self.addr, self.action)?,
+        }
+        if !self.also.is_empty() {
+            let also: Vec<String> = self.also.iter().map(|x| x.to_string()).collect();
+            write!(f, ", also matches rules [{}]", also.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+
+// `set` is the mask normalized once on push, so lookups binary search it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub action: Action,
+    pub mask: Mask,
+    set: ProtocolMap<MaskSet>,
+}
+
+
+// Ordered ACL: the first rule matching a socket decides, unmatched sockets are denied.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+
+impl RuleSet {
+
+    pub fn new() -> Self { Self { rules: vec![] } }
+
+    // Rules usually come from config, so masks are validated before they are added.
+    pub fn push(&mut self, action: Action, mask: Mask) -> Result<(), RangeError> {
+        mask.validate()?;
+        let set = ProtocolMap::from(&mask);
+        self.rules.push(Rule { action, mask, set });
+        Ok(())
+    }
+
+    pub fn rules(&self) -> &[Rule] { &self.rules }
+
+    pub fn allow(mut self, mask: Mask) -> Result<Self, RangeError> {
+        self.push(Action::Allow, mask)?;
+        Ok(self)
+    }
+
+    pub fn deny(mut self, mask: Mask) -> Result<Self, RangeError> {
+        self.push(Action::Deny, mask)?;
+        Ok(self)
+    }
+
+    pub fn evaluate(&self, protocol: u8, ip: u32, port: u16) -> Action {
+        self.rules.iter()
+            .find(|rule| rule.set.contains(protocol, ip, port))
+            .map_or(Action::Deny, |rule| rule.action)
+    }
+
+    pub fn explain(&self, protocol: u8, ip: u32, port: u16) -> Explanation {
+
+        let mut matches = self.rules.iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.set.contains(protocol, ip, port))
+            .map(|(i, _)| i);
+
+        let rule = matches.next();
+
+        Explanation {
+            protocol,
+            addr: SocketAddrV4::new(Ipv4Addr::from(ip), port),
+            rule,
+            action: rule.map_or(Action::Deny, |i| self.rules[i].action),
+            also: matches.collect(),
+        }
+
+    }
+
+    // Effective allowed sockets per protocol. Rules are applied last to first,
+    // so earlier rules overwrite later ones on the sockets they both match.
+    pub fn compile(&self) -> ProtocolMap<RangeSet<Socket>> {
+
+        let mut allowed: ProtocolMap<RangeSet<Socket>> = ProtocolMap::empty();
+
+        for rule in self.rules.iter().rev() {
+            let sockets = ProtocolMap::from(&rule.mask);
+            allowed = match rule.action {
+                Action::Allow => &allowed | &sockets,
+                Action::Deny => &allowed - &sockets,
+            };
+        }
+
+        allowed
+
+    }
+
+    pub fn diff(&self, new: &RuleSet) -> PolicyDiff {
+
+        let old = self.compile();
+        let new = new.compile();
+
+        PolicyDiff {
+            added: ProtocolMap::<MaskSet>::from_collapsed(&(&new - &old)).to_masks(),
+            removed: ProtocolMap::<MaskSet>::from_collapsed(&(&old - &new)).to_masks(),
+        }
+
+    }
+
+    // Compares every rule with the rules before it, in mask space.
+    pub fn analyze(&self) -> Vec<Finding> {
+
+        let mut decided: Vec<ProtocolMap<MaskSet>> = vec![];
+        let mut covered = ProtocolMap::empty();
+        let mut findings = vec![];
+
+        for (i, rule) in self.rules.iter().enumerate() {
+
+            let set = &rule.set;
+            let fresh = set - &covered;
+
+            if set.is_empty() {
+
+                findings.push(Finding::Empty { rule: i });
+
+            } else if fresh.is_empty() {
+
+                let (same, other): (Vec<usize>, Vec<usize>) = (0..i)
+                    .filter(|&j| !(set & &decided[j]).is_empty())
+                    .partition(|&j| self.rules[j].action == rule.action);
+
+                findings.push(match (same.is_empty(), other.is_empty()) {
+                    (true, true) => Finding::Empty { rule: i },
+                    (false, true) => Finding::Redundant { rule: i, by: same },
+                    (true, false) => Finding::Shadowed { rule: i, by: other },
+                    (false, false) => Finding::Mixed { rule: i, same, other },
+                });
+
+            } else {
+
+                for (j, earlier) in self.rules[..i].iter().enumerate() {
+                    let region = set & &earlier.set;
+                    if !region.is_empty() {
+                        findings.push(Finding::Overlap { rule: i, with: j, region });
+                    }
+                }
+
+            }
+
+            covered = &covered | set;
+            decided.push(fresh);
+
+        }
+
+        findings
+
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ranges::types::{Port, IP, MAX_PROTOCOL};
+
+    fn rules() -> Result<RuleSet, RangeError> {
+        RuleSet::new()
+            .deny(Mask::new(vec![IP::new(5, 5)], vec![Port::new(0, 100)]))?
+            .allow(Mask::new(vec![IP::new(0, 9)], vec![Port::new(22, 22), Port::new(80, 80)]))?
+            .deny(Mask::new(vec![IP::new(0, 20)], vec![Port::new(80, 443)]))?
+            .allow(Mask::new(vec![IP::new(8, 15)], vec![Port::new(0, 1000)]))
+    }
+
+    #[test]
+    fn test_push_rejects_invalid_mask() {
+
+        let inverted = Mask::new(vec![IP::new(10, 3)], vec![Port::new(80, 80)]);
+        assert_eq!(RuleSet::new().allow(inverted).err(), Some(RangeError::Inverted { begin: 10, end: 3 }));
+
+        let mut rules = RuleSet::new();
+        let protocols = Mask::new(vec![IP::new(0, 9)], vec![Port::new(80, 80)])
+            .with_protocols(vec![Protocol::new(17, 6)]);
+        assert_eq!(rules.push(Action::Deny, protocols), Err(RangeError::Inverted { begin: 17, end: 6 }));
+        assert!(rules.rules().is_empty());
+
+    }
+
+    #[test]
+    fn test_evaluate() -> Result<(), RangeError> {
+
+        let rules = rules()?;
+
+        assert_eq!(rules.evaluate(6, 5, 22), Action::Deny);
+        assert_eq!(rules.evaluate(6, 4, 22), Action::Allow);
+        assert_eq!(rules.evaluate(6, 4, 80), Action::Allow);
+        assert_eq!(rules.evaluate(6, 12, 80), Action::Deny);
+        assert_eq!(rules.evaluate(6, 12, 500), Action::Allow);
+        assert_eq!(rules.evaluate(6, 30, 22), Action::Deny);
+        assert_eq!(RuleSet::new().evaluate(6, 0, 0), Action::Deny);
+
+        Ok(())
+
+    }
+
+    #[test]
+    fn test_compile_matches_evaluate() -> Result<(), RangeError> {
+
+        let rules = rules()?;
+        let allowed = rules.compile();
+
+        for ip in 0..=25 {
+            for port in 0..=1100 {
+                let expected = rules.evaluate(6, ip, port) == Action::Allow;
+                assert_eq!(allowed.contains(6, ip, port), expected, "{ip}:{port}");
+            }
+        }
+
+        assert!(RuleSet::new().compile().is_empty());
+
+        Ok(())
+
+    }
+
+    #[test]
+    fn test_analyze() -> Result<(), RangeError> {
+
+        let web = Mask::new(vec![IP::new(0, 9)], vec![Port::new(80, 80), Port::new(443, 443)]);
+        let ssh = Mask::new(vec![IP::new(0, 9)], vec![Port::new(22, 22)]);
+
+        let rules = RuleSet::new()
+            .allow(web)?
+            .deny(ssh)?
+            .deny(Mask::new(vec![IP::new(2, 3)], vec![Port::new(443, 443)]))?
+            .allow(Mask::new(vec![IP::new(4, 4)], vec![Port::new(22, 22)]))?
+            .allow(Mask::new(vec![IP::new(5, 5)], vec![Port::new(80, 80)]))?
+            .deny(Mask::new(vec![IP::new(8, 12)], vec![Port::new(20, 22)]))?
+            .allow(Mask::new(vec![IP::new(0, 9)], vec![Port::new(22, 22), Port::new(80, 80)]))?;
+
+        let findings = rules.analyze();
+
+        assert_eq!(findings, vec![
+            Finding::Shadowed { rule: 2, by: vec![0] },
+            Finding::Shadowed { rule: 3, by: vec![1] },
+            Finding::Redundant { rule: 4, by: vec![0] },
+            Finding::Overlap {
+                rule: 5,
+                with: 1,
+                region: ProtocolMap::from(&Mask::new(vec![IP::new(8, 9)], vec![Port::new(22, 22)])),
+            },
+            Finding::Mixed { rule: 6, same: vec![0], other: vec![1] },
+        ]);
+
+        assert_eq!(findings[0].to_string(), "rule 2 is shadowed by rules [0]");
+        assert_eq!(findings[3].to_string(), "rule 5 overlaps rule 1 on {[0.0.0.8/31] x [22] x [any]}");
+
+        Ok(())
+
+    }
+
+    #[test]
+    fn test_analyze_mixed_cover() -> Result<(), RangeError> {
+
+        let rules = RuleSet::new()
+            .allow(Mask::new(vec![IP::new(0, 4)], vec![Port::new(80, 80)]))?
+            .deny(Mask::new(vec![IP::new(5, 9)], vec![Port::new(80, 80)]))?
+            .allow(Mask::new(vec![IP::new(0, 9)], vec![Port::new(80, 80)]))?
+            .allow(Mask::new(vec![IP::new(20, 29)], vec![Port::new(80, 80)]))?;
+
+        let findings = rules.analyze();
+        assert_eq!(findings, vec![Finding::Mixed { rule: 2, same: vec![0], other: vec![1] }]);
+        assert_eq!(findings[0].to_string(), "rule 2 is covered by rules [0] with the same action and [1] with another");
+        assert!(RuleSet::new().analyze().is_empty());
+
+        Ok(())
+
+    }
+
+    #[test]
+    fn test_analyze_empty_rule() -> Result<(), RangeError> {
+
+        let rules = RuleSet::new()
+            .allow(Mask::new(vec![IP::new(0, 9)], vec![Port::new(80, 80)]))?
+            .deny(Mask::new(vec![IP::new(0, 9)], vec![]))?
+            .deny(Mask::new(vec![IP::new(20, 29)], vec![Port::new(80, 80)]).with_protocols(vec![]))?;
+
+        let findings = rules.analyze();
+        assert_eq!(findings, vec![Finding::Empty { rule: 1 }, Finding::Empty { rule: 2 }]);
+        assert_eq!(findings[0].to_string(), "rule 1 matches nothing");
+
+        // Bypasses `push`, which rejects the inverted range.
+        let mask = Mask::new(vec![IP::new(10, 3)], vec![Port::new(80, 80)]);
+        let set = ProtocolMap::from(&mask);
+        let rules = RuleSet { rules: vec![Rule { action: Action::Allow, mask, set }] };
+        assert_eq!(rules.analyze(), vec![Finding::Empty { rule: 0 }]);
+
+        Ok(())
+
+    }
+
+    #[test]
+    fn test_diff() -> Result<(), RangeError> {
+
+        let old = rules()?;
+        let new = RuleSet::new()
+            .deny(Mask::new(vec![IP::new(5, 6)], vec![Port::new(0, 100)]))?
+            .allow(Mask::new(vec![IP::new(0, 9)], vec![Port::new(22, 22), Port::new(80, 80)]))?
+            .deny(Mask::new(vec![IP::new(0, 20)], vec![Port::new(80, 443)]))?
+            .allow(Mask::new(vec![IP::new(8, 15)], vec![Port::new(0, 1000)]))?
+            .allow(Mask::new(vec![IP::new(16, 16)], vec![Port::new(8080, 8080)]))?;
+
+        let diff = old.diff(&new);
+
+        assert_eq!(diff, PolicyDiff {
+            added: vec![Mask::new(vec![IP::new(16, 16)], vec![Port::new(8080, 8080)])],
+            removed: vec![
+                Mask::new(vec![IP::new(6, 6)], vec![Port::new(22, 22), Port::new(80, 80)]),
+            ],
+        });
+        assert_eq!(diff.to_string(), "+ [0.0.0.16] x [8080] x [any]\n- [0.0.0.6] x [22, 80] x [any]\n");
+
+        assert_eq!(new.diff(&old).added, diff.removed);
+        assert!(old.diff(&old).is_empty());
+
+        Ok(())
+
+    }
+
+    #[test]
+    fn test_explain() -> Result<(), RangeError> {
+
+        let rules = rules()?;
+
+        let explanation = rules.explain(6, 5, 80);
+        assert_eq!(explanation, Explanation {
+            protocol: 6,
+            addr: "0.0.0.5:80".parse().unwrap(),
+            rule: Some(0),
+            action: Action::Deny,
+            also: vec![1, 2],
+        });
+        assert_eq!(explanation.to_string(), "tcp 0.0.0.5:80 deny by rule 0, also matches rules [1, 2]");
+
+        let explanation = rules.explain(6, 9, 80);
+        assert_eq!((explanation.rule, explanation.action, explanation.also), (Some(1), Action::Allow, vec![2, 3]));
+
+        let explanation = rules.explain(6, 12, 500);
+        assert_eq!((explanation.rule, explanation.action, explanation.also), (Some(3), Action::Allow, vec![]));
+
+        let explanation = rules.explain(6, 30, 22);
+        assert_eq!(explanation.to_string(), "tcp 0.0.0.30:22 deny by default");
+
+        for ip in 0..=25 {
+            for port in [0, 22, 80, 100, 101, 443, 1000, 1001] {
+                assert_eq!(rules.explain(6, ip, port).action, rules.evaluate(6, ip, port));
+            }
+        }
+
+        Ok(())
+
+    }
+
+    #[test]
+    fn test_lookup_unsorted_mask() -> Result<(), RangeError> {
+
+        let mask = Mask::new(
+            vec![IP::new(5, 7), IP::new(1, 2), IP::new(6, 9)],
+            vec![Port::new(443, 443), Port::new(80, 80)],
+        ).with_protocols(vec![Protocol::UDP, Protocol::TCP]);
+        let rules = RuleSet::new().allow(mask.clone())?;
+
+        for ip in 0..=10 {
+            for port in [79, 80, 81, 443] {
+                for protocol in [1, 6, 17] {
+                    let allowed = rules.evaluate(protocol, ip, port) == Action::Allow;
+                    assert_eq!(allowed, mask.contains(protocol, ip, port), "{protocol} {ip}:{port}");
+                }
+            }
+        }
+
+        assert_eq!(rules.rules()[0].mask, mask);
+
+        Ok(())
+
+    }
+
+    #[test]
+    fn test_protocols() -> Result<(), RangeError> {
+
+        let dns = |protocols| Mask::new(vec![IP::new(0, 9)], vec![Port::new(53, 53)])
+            .with_protocols(protocols);
+
+        let rules = RuleSet::new()
+            .deny(dns(vec![Protocol::TCP]))?
+            .allow(dns(vec![Protocol::TCP, Protocol::UDP]))?;
+
+        assert_eq!(rules.evaluate(6, 1, 53), Action::Deny);
+        assert_eq!(rules.evaluate(17, 1, 53), Action::Allow);
+        assert_eq!(rules.evaluate(1, 1, 53), Action::Deny);
+        assert_eq!(rules.explain(17, 1, 53).to_string(), "udp 0.0.0.1:53 allow by rule 1");
+
+        let allowed = rules.compile();
+        assert!(allowed.contains(17, 1, 53));
+        assert!(!allowed.contains(6, 1, 53));
+        assert_eq!(allowed.entries().len(), 1);
+
+        let region = ProtocolMap::from(&dns(vec![Protocol::TCP]));
+        assert_eq!(rules.analyze(), vec![Finding::Overlap { rule: 1, with: 0, region }]);
+
+        let new = RuleSet::new().allow(dns(vec![Protocol::ANY]))?;
+        assert_eq!(rules.diff(&new).added, vec![
+            dns(vec![Protocol::new(0, 16), Protocol::new(18, MAX_PROTOCOL)]),
+        ]);
+        assert!(rules.diff(&new).removed.is_empty());
+
+        Ok(())
+
+    }
+
+}
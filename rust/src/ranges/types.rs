@@ -1,72 +1,196 @@
 #![allow(dead_code)]
 
 use std::fmt;
+use std::hash::Hash;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4};
+
+use super::error::RangeError;
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum TypeRange {Port, IP, Socket}
+pub enum TypeRange {Port, IP, Socket, IPv6, SocketV6, Protocol}
 
-pub const MAX_IP: u64 = u64::pow(2, 32) - 1;
-pub const MAX_PORT: u64 = u64::pow(2, 16) - 1;
+pub const MAX_IP: u32 = u32::MAX;
+pub const MAX_PORT: u16 = u16::MAX;
+pub const MAX_PROTOCOL: u8 = u8::MAX;
 pub const MAX_SOCKET: u64 = u64::pow(2, 32 + 16) - 1;
+pub const MAX_IPV6: u128 = u128::MAX;
+pub const MAX_SOCKET_V6: Endpoint6 = Endpoint6 { ip: u128::MAX, port: u16::MAX };
+
+
+// The integer-like type of range bounds. Only what the set algebra needs:
+// ordering, stepping by one and measuring a distance. It is not num's PrimInt
+// because the IPv6 socket space is 144 bits wide and has no primitive type.
+pub trait Bound: Copy + Ord + Hash + fmt::Debug + fmt::Display {
+
+    const ZERO: Self;
+
+    // The next and the previous value, the caller makes sure they exist.
+    fn succ(self) -> Self;
+
+    fn pred(self) -> Self;
+
+    // self - lower, for lower <= self.
+    fn distance(self, lower: Self) -> Self;
+
+    fn to_u128(self) -> Option<u128>;
+
+    fn to_f64(self) -> f64;
 
+}
+
+
+macro_rules! impl_bound {
+    ($($t:ty),*) => {$(
 
-pub trait Range {
+        impl Bound for $t {
 
-    fn min(&self) -> u64 { 0 }
+            const ZERO: Self = 0;
 
-    fn max(&self) -> u64;
+            fn succ(self) -> Self { self + 1 }
 
-    fn range_of(&self) -> TypeRange;
+            fn pred(self) -> Self { self - 1 }
 
-    fn begin(&self) -> u64;
+            fn distance(self, lower: Self) -> Self { self - lower }
 
-    fn end(&self) -> u64;
+            fn to_u128(self) -> Option<u128> { Some(self as u128) }
 
-    fn set_end(&mut self, val: u64);
+            fn to_f64(self) -> f64 { self as f64 }
 
-    fn clone_dyn(&self) -> Box<dyn Range>;
+        }
 
+    )*};
 }
 
+impl_bound!(u8, u16, u32, u64, u128);
+
+
+// A point of the IPv6 socket space, ordered as the 144-bit number `ip << 16 | port`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Endpoint6 { pub ip: u128, pub port: u16 }
 
-impl Clone for Box<dyn Range> {
 
-    fn clone(&self) -> Self { self.clone_dyn() }
+impl Endpoint6 {
+
+    pub fn new(ip: u128, port: u16) -> Self { Self { ip, port } }
 
 }
 
-impl fmt::Display for Box<dyn Range> {
+
+impl fmt::Display for Endpoint6 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            format!("({}, {})", self.begin(), self.end())
-        )
+        write!(f, "[{}]:{}", Ipv6Addr::from(self.ip), self.port)
+    }
+}
+
+
+impl Bound for Endpoint6 {
+
+    const ZERO: Self = Endpoint6 { ip: 0, port: 0 };
+
+    fn succ(self) -> Self {
+        match self.port.checked_add(1) {
+            Some(port) => Endpoint6 { ip: self.ip, port },
+            None => Endpoint6 { ip: self.ip + 1, port: 0 },
+        }
+    }
+
+    fn pred(self) -> Self {
+        match self.port.checked_sub(1) {
+            Some(port) => Endpoint6 { ip: self.ip, port },
+            None => Endpoint6 { ip: self.ip - 1, port: u16::MAX },
+        }
+    }
+
+    fn distance(self, lower: Self) -> Self {
+        match self.port.checked_sub(lower.port) {
+            Some(port) => Endpoint6 { ip: self.ip - lower.ip, port },
+            None => Endpoint6 { ip: self.ip - lower.ip - 1, port: self.port.wrapping_sub(lower.port) },
+        }
+    }
+
+    fn to_u128(self) -> Option<u128> {
+        self.ip.checked_mul(1 << 16)?.checked_add(self.port as u128)
     }
+
+    fn to_f64(self) -> f64 { self.ip as f64 * 65536.0 + self.port as f64 }
+
 }
 
-#[derive(Clone, Copy, PartialEq)]
-pub struct Port { pub begin: u64, pub end: u64}
 
+pub trait Range: Copy + fmt::Display {
+
+    type Bound: Bound;
+
+    const MIN: Self::Bound = <Self::Bound as Bound>::ZERO;
+
+    const MAX: Self::Bound;
+
+    const RANGE_OF: TypeRange;
+
+    fn from_bounds(begin: Self::Bound, end: Self::Bound) -> Self;
+
+    fn try_from_bounds(begin: Self::Bound, end: Self::Bound) -> Result<Self, RangeError<Self::Bound>> {
 
-#[derive(Clone, Copy, PartialEq)]
-pub struct IP { pub begin: u64, pub end: u64}
+        if begin > end {
+            return Err(RangeError::Inverted { begin, end });
+        }
+        if end > Self::MAX {
+            return Err(RangeError::OutOfBounds { value: end, max: Self::MAX });
+        }
 
+        Ok(Self::from_bounds(begin, end))
 
-#[derive(Clone, Copy, PartialEq)]
+    }
+
+    fn range_of(&self) -> TypeRange { Self::RANGE_OF }
+
+    fn begin(&self) -> Self::Bound;
+
+    fn end(&self) -> Self::Bound;
+
+    fn set_end(&mut self, val: Self::Bound);
+
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Port { pub begin: u16, pub end: u16}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IP { pub begin: u32, pub end: u32}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Socket { pub begin: u64, pub end: u64}
 
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IPv6 { pub begin: u128, pub end: u128}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SocketV6 { pub begin: Endpoint6, pub end: Endpoint6}
+
+
+// IP protocol numbers, as in the IPv4 header.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Protocol { pub begin: u8, pub end: u8}
+
+
 impl Port {
 
-    pub fn new(begin: u64, end: u64) -> Self {  Self { begin, end } }
+    pub fn new(begin: u16, end: u16) -> Self {  Self { begin, end } }
+
+    pub fn try_new(begin: u16, end: u16) -> Result<Self, RangeError<u16>> { Self::try_from_bounds(begin, end) }
 
 }
 
 
 impl IP {
 
-    pub fn new(begin: u64, end: u64) -> Self {  Self { begin, end } }
+    pub fn new(begin: u32, end: u32) -> Self {  Self { begin, end } }
+
+    pub fn try_new(begin: u32, end: u32) -> Result<Self, RangeError<u32>> { Self::try_from_bounds(begin, end) }
 
 }
 
@@ -75,69 +199,299 @@ impl Socket {
 
     pub fn new(begin: u64, end: u64) -> Self {  Self { begin, end } }
 
+    pub fn try_new(begin: u64, end: u64) -> Result<Self, RangeError> { Self::try_from_bounds(begin, end) }
+
+}
+
+
+impl IPv6 {
+
+    pub fn new(begin: u128, end: u128) -> Self {  Self { begin, end } }
+
+    pub fn try_new(begin: u128, end: u128) -> Result<Self, RangeError<u128>> { Self::try_from_bounds(begin, end) }
+
+}
+
+
+impl SocketV6 {
+
+    pub fn new(begin: Endpoint6, end: Endpoint6) -> Self {  Self { begin, end } }
+
+    pub fn try_new(begin: Endpoint6, end: Endpoint6) -> Result<Self, RangeError<Endpoint6>> {
+        Self::try_from_bounds(begin, end)
+    }
+
+}
+
+
+impl Protocol {
+
+    pub const ICMP: Protocol = Protocol { begin: 1, end: 1 };
+
+    pub const TCP: Protocol = Protocol { begin: 6, end: 6 };
+
+    pub const UDP: Protocol = Protocol { begin: 17, end: 17 };
+
+    pub const ANY: Protocol = Protocol { begin: 0, end: MAX_PROTOCOL };
+
+    pub fn new(begin: u8, end: u8) -> Self {  Self { begin, end } }
+
+    pub fn try_new(begin: u8, end: u8) -> Result<Self, RangeError<u8>> { Self::try_from_bounds(begin, end) }
+
 }
 
 
 impl fmt::Debug for Port {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", format!("({}, {})", &self.begin, &self.end))
+        write!(f, "({}, {})", &self.begin, &self.end)
     }
 }
 
 
 impl fmt::Debug for IP {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", format!("({}, {})", &self.begin, &self.end))
+        write!(f, "({}, {})", &self.begin, &self.end)
     }
 }
 
 
 impl fmt::Debug for Socket {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", format!("({}, {})", &self.begin, &self.end))
+        write!(f, "({}, {})", &self.begin, &self.end)
+    }
+}
+
+
+// Human readable forms: `80-443`, `10.0.0.0/8`, `10.0.0.1:80-10.0.0.1:443`.
+// The alternate flag `{:#}` prints the raw integer bounds instead.
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "({}, {})", self.begin, self.end)
+        } else if self.begin == self.end {
+            write!(f, "{}", self.begin)
+        } else {
+            write!(f, "{}-{}", self.begin, self.end)
+        }
+    }
+}
+
+
+impl fmt::Display for IP {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        let begin = Ipv4Addr::from(self.begin);
+        let end = Ipv4Addr::from(self.end);
+
+        if f.alternate() {
+            write!(f, "({}, {})", self.begin, self.end)
+        } else if self.begin == self.end {
+            write!(f, "{begin}")
+        } else if let Some(host_bits) = self.end.checked_sub(self.begin)
+            && host_bits & host_bits.wrapping_add(1) == 0
+            && self.begin & host_bits == 0
+        {
+            write!(f, "{begin}/{}", 32 - host_bits.count_ones())
+        } else {
+            write!(f, "{begin}-{end}")
+        }
+
+    }
+}
+
+
+impl fmt::Display for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "({}, {})", self.begin, self.end)
+        } else {
+            write!(f, "{}", SocketAddrRange::from(*self))
+        }
+    }
+}
+
+
+pub fn convert(a: u32, b: u16) -> u64 {
+
+    let a_bytes = a.to_be_bytes();
+    let b_bytes = b.to_be_bytes();
+
+    let buff = [a_bytes.as_slice(), b_bytes.as_slice()].concat();
+
+    u64::from_be_bytes([
+        0,
+        0,
+        buff[0],
+        buff[1],
+        buff[2],
+        buff[3],
+        buff[4],
+        buff[5],
+    ])
+
+}
+
+
+// Inverse of `convert`.
+pub fn split(value: u64) -> (u32, u16) {
+    ((value >> 16) as u32, value as u16)
+}
+
+
+// Typed view of a `Socket`: both ends as IPv4 address and port instead of packed integers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SocketAddrRange {
+    pub begin: SocketAddrV4,
+    pub end: SocketAddrV4,
+}
+
+
+impl SocketAddrRange {
+
+    pub fn new(begin: SocketAddrV4, end: SocketAddrV4) -> Self { Self { begin, end } }
+
+    pub fn ip(&self) -> IP {
+        IP::new(u32::from(*self.begin.ip()), u32::from(*self.end.ip()))
+    }
+
+    // The covered ports, only when both ends share an address; otherwise the
+    // range wraps through whole addresses and is no single port range.
+    pub fn port(&self) -> Option<Port> {
+        (self.begin.ip() == self.end.ip()).then(|| Port::new(self.begin.port(), self.end.port()))
+    }
+
+}
+
+
+fn to_addr(value: u64) -> SocketAddrV4 {
+    let (ip, port) = split(value);
+    SocketAddrV4::new(Ipv4Addr::from(ip), port)
+}
+
+
+fn from_addr(addr: &SocketAddrV4) -> u64 {
+    convert(u32::from(*addr.ip()), addr.port())
+}
+
+
+impl From<Socket> for SocketAddrRange {
+    fn from(socket: Socket) -> Self { Self::new(to_addr(socket.begin), to_addr(socket.end)) }
+}
+
+
+impl From<SocketAddrRange> for Socket {
+    fn from(range: SocketAddrRange) -> Self { Socket::new(from_addr(&range.begin), from_addr(&range.end)) }
+}
+
+
+impl fmt::Display for SocketAddrRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.begin == self.end {
+            write!(f, "{}", self.begin)
+        } else {
+            write!(f, "{}-{}", self.begin, self.end)
+        }
+    }
+}
+
+
+impl fmt::Debug for IPv6 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", &self.begin, &self.end)
+    }
+}
+
+
+impl fmt::Debug for SocketV6 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", &self.begin, &self.end)
+    }
+}
+
+
+impl fmt::Display for IPv6 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        let begin = Ipv6Addr::from(self.begin);
+        let end = Ipv6Addr::from(self.end);
+
+        if f.alternate() {
+            write!(f, "({}, {})", self.begin, self.end)
+        } else if self.begin == self.end {
+            write!(f, "{begin}")
+        } else if let Some(host_bits) = self.end.checked_sub(self.begin)
+            && host_bits & host_bits.wrapping_add(1) == 0
+            && self.begin & host_bits == 0
+        {
+            write!(f, "{begin}/{}", 128 - host_bits.count_ones())
+        } else {
+            write!(f, "{begin}-{end}")
+        }
+
+    }
+}
+
+
+impl fmt::Display for SocketV6 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "({:?}, {:?})", self.begin, self.end)
+        } else if self.begin == self.end {
+            write!(f, "{}", self.begin)
+        } else {
+            write!(f, "{}-{}", self.begin, self.end)
+        }
     }
 }
 
 
 impl Range for Port {
 
-    fn max(&self) -> u64 { MAX_PORT }
+    type Bound = u16;
 
-    fn range_of(&self) -> TypeRange { TypeRange::Port }
+    const MAX: u16 = MAX_PORT;
 
-    fn begin(&self) -> u64 { self.begin }
+    const RANGE_OF: TypeRange = TypeRange::Port;
 
-    fn end(&self) -> u64 { self.end  }
+    fn from_bounds(begin: u16, end: u16) -> Self { Self { begin, end } }
 
-    fn set_end(&mut self, val: u64) { self.end = val; }
+    fn begin(&self) -> u16 { self.begin }
 
-    fn clone_dyn(&self) -> Box<dyn Range> { Box::new(self.clone()) }
+    fn end(&self) -> u16 { self.end  }
+
+    fn set_end(&mut self, val: u16) { self.end = val; }
 
 }
 
 
 impl Range for IP {
 
-    fn max(&self) -> u64 { MAX_IP  }
+    type Bound = u32;
 
-    fn range_of(&self) -> TypeRange { TypeRange::IP }
+    const MAX: u32 = MAX_IP;
 
-    fn begin(&self) -> u64 { self.begin }
+    const RANGE_OF: TypeRange = TypeRange::IP;
 
-    fn end(&self) -> u64 { self.end  }
+    fn from_bounds(begin: u32, end: u32) -> Self { Self { begin, end } }
 
-    fn set_end(&mut self, val: u64) { self.end = val; }
+    fn begin(&self) -> u32 { self.begin }
 
-    fn clone_dyn(&self) -> Box<dyn Range> { Box::new(self.clone()) }
+    fn end(&self) -> u32 { self.end  }
+
+    fn set_end(&mut self, val: u32) { self.end = val; }
 
 }
 
 
 impl Range for Socket {
 
-    fn max(&self) -> u64 { MAX_SOCKET }
+    type Bound = u64;
+
+    const MAX: u64 = MAX_SOCKET;
 
-    fn range_of(&self) -> TypeRange { TypeRange::Socket }
+    const RANGE_OF: TypeRange = TypeRange::Socket;
+
+    fn from_bounds(begin: u64, end: u64) -> Self { Self { begin, end } }
 
     fn begin(&self) -> u64 { self.begin }
 
@@ -145,7 +499,91 @@ impl Range for Socket {
 
     fn set_end(&mut self, val: u64) { self.end = val; }
 
-    fn clone_dyn(&self) -> Box<dyn Range> { Box::new(self.clone()) }
+}
+
+
+impl Range for IPv6 {
+
+    type Bound = u128;
+
+    const MAX: u128 = MAX_IPV6;
+
+    const RANGE_OF: TypeRange = TypeRange::IPv6;
+
+    fn from_bounds(begin: u128, end: u128) -> Self { Self { begin, end } }
+
+    fn begin(&self) -> u128 { self.begin }
+
+    fn end(&self) -> u128 { self.end  }
+
+    fn set_end(&mut self, val: u128) { self.end = val; }
+
+}
+
+
+impl Range for SocketV6 {
+
+    type Bound = Endpoint6;
+
+    const MAX: Endpoint6 = MAX_SOCKET_V6;
+
+    const RANGE_OF: TypeRange = TypeRange::SocketV6;
+
+    fn from_bounds(begin: Endpoint6, end: Endpoint6) -> Self { Self { begin, end } }
+
+    fn begin(&self) -> Endpoint6 { self.begin }
+
+    fn end(&self) -> Endpoint6 { self.end  }
+
+    fn set_end(&mut self, val: Endpoint6) { self.end = val; }
+
+}
+
+
+impl fmt::Debug for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", &self.begin, &self.end)
+    }
+}
+
+
+// Well known protocols by name: `tcp`, `udp`, `icmp`, `any`, otherwise `47` or `50-51`.
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "({}, {})", self.begin, self.end)
+        } else if *self == Protocol::ICMP {
+            write!(f, "icmp")
+        } else if *self == Protocol::TCP {
+            write!(f, "tcp")
+        } else if *self == Protocol::UDP {
+            write!(f, "udp")
+        } else if *self == Protocol::ANY {
+            write!(f, "any")
+        } else if self.begin == self.end {
+            write!(f, "{}", self.begin)
+        } else {
+            write!(f, "{}-{}", self.begin, self.end)
+        }
+    }
+}
+
+
+impl Range for Protocol {
+
+    type Bound = u8;
+
+    const MAX: u8 = MAX_PROTOCOL;
+
+    const RANGE_OF: TypeRange = TypeRange::Protocol;
+
+    fn from_bounds(begin: u8, end: u8) -> Self { Self { begin, end } }
+
+    fn begin(&self) -> u8 { self.begin }
+
+    fn end(&self) -> u8 { self.end  }
+
+    fn set_end(&mut self, val: u8) { self.end = val; }
 
 }
 
@@ -155,18 +593,54 @@ mod test_types {
 
     use super::*;
 
+    #[test]
+    fn test_convert() {
+        assert_eq!(convert(10, 20), 655380);
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(655380), (10, 20));
+        assert_eq!(split(convert(u32::MAX, MAX_PORT)), (u32::MAX, MAX_PORT));
+    }
+
+    #[test]
+    fn test_socket_addr_range() {
+
+        let socket = Socket::new(convert(0x0a000001, 80), convert(0x0a000002, 443));
+        let range = SocketAddrRange::from(socket);
+
+        assert_eq!(range.begin, "10.0.0.1:80".parse().unwrap());
+        assert_eq!(range.end, "10.0.0.2:443".parse().unwrap());
+        assert_eq!(range.ip(), IP::new(0x0a000001, 0x0a000002));
+        assert_eq!(range.port(), None);
+        assert_eq!(Socket::from(range), socket);
+        assert_eq!(range.to_string(), "10.0.0.1:80-10.0.0.2:443");
+
+        let single = SocketAddrRange::from(Socket::new(convert(u32::MAX, MAX_PORT), convert(u32::MAX, MAX_PORT)));
+        assert_eq!(single.to_string(), "255.255.255.255:65535");
+        assert_eq!(single.port(), Some(Port::new(MAX_PORT, MAX_PORT)));
+
+        let inverted = SocketAddrRange::from(Socket::new(convert(1, 500), convert(2, 100)));
+        assert_eq!(inverted.ip(), IP::new(1, 2));
+        assert_eq!(inverted.port(), None);
+        assert_eq!(SocketAddrRange::from(Socket::new(convert(1, 100), convert(1, 500))).port(), Some(Port::new(100, 500)));
+        assert_eq!(Socket::from(single).end, MAX_SOCKET);
+
+    }
+
     #[test]
     fn test_range_port() {
 
-        let begin: u64 = 0;
-        let end: u64 = 1000;
+        let begin: u16 = 0;
+        let end: u16 = 1000;
 
         let port: Port = Port::new(begin, end);
 
         assert_eq!(port.begin, begin);
         assert_eq!(port.end, end);
-        assert_eq!(port.min(), 0);
-        assert_eq!(port.max(), MAX_PORT);
+        assert_eq!(Port::MIN, 0);
+        assert_eq!(Port::MAX, MAX_PORT);
         assert_eq!(port.range_of(), TypeRange::Port );
         assert_eq!(format!("{:?}", port), format!("({begin}, {end})"));
 
@@ -175,15 +649,15 @@ mod test_types {
     #[test]
     fn test_range_ip() {
 
-        let begin: u64 = 0;
-        let end: u64 = 1000;
+        let begin: u32 = 0;
+        let end: u32 = 1000;
 
         let ip: IP = IP::new(begin, end);
 
         assert_eq!(ip.begin, begin);
         assert_eq!(ip.end, end);
-        assert_eq!(ip.min(), 0);
-        assert_eq!(ip.max(), MAX_IP);
+        assert_eq!(IP::MIN, 0);
+        assert_eq!(IP::MAX, MAX_IP);
         assert_eq!(ip.range_of(), TypeRange::IP );
         assert_eq!(format!("{:?}", ip), format!("({begin}, {end})"));
 
@@ -199,11 +673,140 @@ mod test_types {
 
         assert_eq!(socket.begin, begin);
         assert_eq!(socket.end, end);
-        assert_eq!(socket.min(), 0);
-        assert_eq!(socket.max(), MAX_SOCKET);
+        assert_eq!(Socket::MIN, 0);
+        assert_eq!(Socket::MAX, MAX_SOCKET);
         assert_eq!(socket.range_of(), TypeRange::Socket );
         assert_eq!(format!("{:?}", socket), format!("({begin}, {end})"));
 
     }
 
+    #[test]
+    fn test_try_new() {
+
+        assert_eq!(Port::try_new(80, 443), Ok(Port::new(80, 443)));
+        assert_eq!(Port::try_new(443, 80), Err(RangeError::Inverted { begin: 443, end: 80 }));
+
+        assert_eq!(IP::try_new(0, MAX_IP), Ok(IP::new(0, MAX_IP)));
+        assert_eq!(IP::try_new(10, 3), Err(RangeError::Inverted { begin: 10, end: 3 }));
+
+        assert_eq!(Socket::try_new(0, MAX_SOCKET), Ok(Socket::new(0, MAX_SOCKET)));
+        assert_eq!(Socket::try_new(0, u64::MAX), Err(RangeError::OutOfBounds { value: u64::MAX, max: MAX_SOCKET }));
+
+    }
+
+    #[test]
+    fn test_display_port() {
+
+        assert_eq!(format!("{}", Port::new(80, 443)), "80-443");
+        assert_eq!(format!("{}", Port::new(22, 22)), "22");
+        assert_eq!(format!("{:#}", Port::new(80, 443)), "(80, 443)");
+
+    }
+
+    #[test]
+    fn test_display_protocol() {
+
+        assert_eq!(format!("{}", Protocol::TCP), "tcp");
+        assert_eq!(format!("{}", Protocol::new(17, 17)), "udp");
+        assert_eq!(format!("{}", Protocol::ICMP), "icmp");
+        assert_eq!(format!("{}", Protocol::ANY), "any");
+        assert_eq!(format!("{}", Protocol::new(47, 47)), "47");
+        assert_eq!(format!("{}", Protocol::new(50, 51)), "50-51");
+        assert_eq!(format!("{:#}", Protocol::TCP), "(6, 6)");
+
+    }
+
+    #[test]
+    fn test_range_protocol() {
+
+        assert_eq!(Protocol::MIN, 0);
+        assert_eq!(Protocol::MAX, MAX_PROTOCOL);
+        assert_eq!(Protocol::TCP.range_of(), TypeRange::Protocol);
+        assert_eq!(Protocol::try_new(17, 6), Err(RangeError::Inverted { begin: 17, end: 6 }));
+        assert_eq!(format!("{:?}", Protocol::UDP), "(17, 17)");
+
+    }
+
+    #[test]
+    fn test_display_ip() {
+
+        assert_eq!(format!("{}", IP::new(167772161, 167772161)), "10.0.0.1");
+        assert_eq!(format!("{}", IP::new(167772160, 184549375)), "10.0.0.0/8");
+        assert_eq!(format!("{}", IP::new(0, MAX_IP)), "0.0.0.0/0");
+        assert_eq!(format!("{}", IP::new(167772161, 167772170)), "10.0.0.1-10.0.0.10");
+        assert_eq!(format!("{}", IP::new(167772160, 167772161)), "10.0.0.0/31");
+        assert_eq!(format!("{}", IP::new(167772161, 167772162)), "10.0.0.1-10.0.0.2");
+        assert_eq!(format!("{:#}", IP::new(655360, 656360)), "(655360, 656360)");
+        assert_eq!(format!("{}", IP::new(10, 3)), "0.0.0.10-0.0.0.3");
+        assert_eq!(format!("{:#}", IP::new(10, 3)), "(10, 3)");
+
+    }
+
+    #[test]
+    fn test_display_socket() {
+
+        let begin = (167772161 << 16) | 80;
+        let end = (167772161 << 16) | 443;
+
+        assert_eq!(format!("{}", Socket::new(begin, end)), "10.0.0.1:80-10.0.0.1:443");
+        assert_eq!(format!("{}", Socket::new(begin, begin)), "10.0.0.1:80");
+        assert_eq!(format!("{}", Socket::new(0, MAX_SOCKET)), "0.0.0.0:0-255.255.255.255:65535");
+        assert_eq!(format!("{:#}", Socket::new(begin, end)), format!("({begin}, {end})"));
+
+    }
+
+    #[test]
+    fn test_range_ipv6() {
+
+        let ip: IPv6 = IPv6::new(0, 1000);
+
+        assert_eq!(IPv6::MIN, 0);
+        assert_eq!(IPv6::MAX, MAX_IPV6);
+        assert_eq!(ip.range_of(), TypeRange::IPv6);
+        assert_eq!(IPv6::try_new(10, 3), Err(RangeError::Inverted { begin: 10, end: 3 }));
+        assert_eq!(format!("{}", IPv6::new(0, MAX_IPV6)), "::/0");
+        assert_eq!(format!("{}", IPv6::new(1, 1)), "::1");
+        assert_eq!(format!("{}", IPv6::new(0x2001_0db8 << 96, (0x2001_0db8 << 96) | (u128::MAX >> 32))), "2001:db8::/32");
+        assert_eq!(format!("{}", IPv6::new(1, 3)), "::1-::3");
+        assert_eq!(format!("{:#}", IPv6::new(1, 3)), "(1, 3)");
+        assert_eq!(format!("{}", IPv6::new(3, 1)), "::3-::1");
+
+    }
+
+    #[test]
+    fn test_range_socket_v6() {
+
+        let begin = Endpoint6::new(1, 80);
+        let end = Endpoint6::new(1, 443);
+        let socket = SocketV6::new(begin, end);
+
+        assert_eq!(SocketV6::MAX, MAX_SOCKET_V6);
+        assert_eq!(socket.range_of(), TypeRange::SocketV6);
+        assert_eq!(SocketV6::try_new(end, begin), Err(RangeError::Inverted { begin: end, end: begin }));
+        assert_eq!(format!("{}", socket), "[::1]:80-[::1]:443");
+
+    }
+
+    #[test]
+    fn test_endpoint6_bound() {
+
+        assert_eq!(Endpoint6::new(1, u16::MAX).succ(), Endpoint6::new(2, 0));
+        assert_eq!(Endpoint6::new(2, 0).pred(), Endpoint6::new(1, u16::MAX));
+        assert_eq!(Endpoint6::new(3, 5).distance(Endpoint6::new(1, 10)), Endpoint6::new(1, u16::MAX - 4));
+        assert_eq!(Endpoint6::new(1, 10).distance(Endpoint6::new(1, 10)), Endpoint6::ZERO);
+        assert_eq!(Endpoint6::new(1, 5).to_u128(), Some(65541));
+        assert_eq!(MAX_SOCKET_V6.to_u128(), None);
+        assert!(Endpoint6::new(1, 0) > Endpoint6::new(0, u16::MAX));
+
+    }
+
+    #[test]
+    fn test_range_sizes() {
+
+        assert_eq!(std::mem::size_of::<Port>(), 4);
+        assert_eq!(std::mem::size_of::<IP>(), 8);
+        assert_eq!(std::mem::size_of::<Socket>(), 16);
+
+    }
+
 }